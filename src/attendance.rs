@@ -0,0 +1,261 @@
+mod error;
+
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+pub use error::Error;
+use error::Result;
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+
+use crate::spreadsheet::Id;
+
+/// The name of the attendance log, which is stored next to the configuration file.
+pub const FILE_NAME: &str = "attendance.jsonl";
+
+/// A single sign-in attempt.
+///
+/// # Fields
+///
+/// * `timestamp` - When the attempt was made, in the kiosk's local time.
+/// * `student_id` - The ID as written in the roster if the student was found, which is what
+///   MyStudio knows them by, or the ID that was entered otherwise.
+/// * `student_name` - The name the ID resolved to, or `None` if the ID is not in the roster.
+/// * `outcome` - What happened to the attempt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    pub student_id: Id,
+    pub student_name: Option<String>,
+    pub outcome: Outcome,
+}
+
+/// The outcome of a sign-in attempt.
+///
+/// # Variants
+///
+/// * `CheckedIn` - MyStudio recorded the check-in.
+/// * `Queued` - MyStudio could not be reached, so the check-in was queued to be sent later.
+/// * `Synced` - A previously queued check-in was sent to MyStudio.
+/// * `NeedsReconciliation` - A queued check-in was not sent because it was made on an earlier day,
+///   which MyStudio would not credit it to. It has to be entered in MyStudio by hand.
+/// * `UnknownId` - The ID is not in the roster, so MyStudio was not contacted.
+/// * `Unconfirmed` - The check-in reached MyStudio but its answer was lost, so it may or may not
+///   have been recorded. It is not sent again, since that could record it twice.
+/// * `Failed` - MyStudio rejected the check-in, or it could not be queued.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    CheckedIn,
+    Queued,
+    Synced,
+    NeedsReconciliation,
+    UnknownId,
+    Unconfirmed { error: String },
+    Failed { error: String },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::CheckedIn => f.write_str("Checked in"),
+            Outcome::Queued => f.write_str("Queued offline"),
+            Outcome::Synced => f.write_str("Synced"),
+            Outcome::NeedsReconciliation => f.write_str("Not synced, enter in MyStudio by hand"),
+            Outcome::UnknownId => f.write_str("Unknown ID"),
+            Outcome::Unconfirmed { error } => write!(f, "Unconfirmed, check MyStudio: {error}"),
+            Outcome::Failed { error } => write!(f, "Failed: {error}"),
+        }
+    }
+}
+
+/// An append-only log of every sign-in attempt made at the kiosk.
+///
+/// The log is stored as one JSON object per line and is never rewritten, so that it can serve as
+/// a record independent of the MyStudio dashboard.
+///
+/// # Fields
+///
+/// * `path` - The path of the log file.
+#[derive(Debug, Clone)]
+pub struct AttendanceLog {
+    path: PathBuf,
+}
+
+impl AttendanceLog {
+    /// Creates a log stored at `path`. The file is created on the first `append`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Appends a record to the log and syncs it to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be serialized or the file cannot be written.
+    pub fn append(&self, record: &Record) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Returns the records made on the given days, oldest first.
+    ///
+    /// Days are compared in the offset each record was made in, so a record belongs to the day it
+    /// was on at the kiosk.
+    ///
+    /// # Arguments
+    ///
+    /// * `dates` - The inclusive range of days to return records for.
+    /// * `student_id` - If `Some`, only records for this student are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the log exists but cannot be read. Lines that cannot be parsed are
+    /// reported and skipped.
+    pub fn query(
+        &self,
+        dates: RangeInclusive<Date>,
+        student_id: Option<&str>,
+    ) -> Result<Vec<Record>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut records = Vec::new();
+        for (index, line) in BufReader::new(File::open(&self.path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Record = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                Err(e) => {
+                    eprintln!(
+                        "Skipping unreadable record on line {} of '{}': {e}",
+                        index + 1,
+                        self.path.display()
+                    );
+                    continue;
+                }
+            };
+
+            if dates.contains(&record.timestamp.date())
+                && student_id.is_none_or(|id| record.student_id == id)
+            {
+                records.push(record);
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::{TempDir, tempdir};
+    use time::{Month, Time, UtcOffset};
+
+    use super::*;
+
+    fn date(day: u8) -> Date {
+        Date::from_calendar_date(2026, Month::April, day).unwrap()
+    }
+
+    /// A record made at `hour` on `day` of April 2026, at the kiosk in UTC+9.
+    fn record(day: u8, hour: u8, student_id: &str) -> Record {
+        Record {
+            timestamp: date(day)
+                .with_time(Time::from_hms(hour, 30, 0).unwrap())
+                .assume_offset(UtcOffset::from_hms(9, 0, 0).unwrap()),
+            student_id: student_id.to_owned(),
+            student_name: None,
+            outcome: Outcome::CheckedIn,
+        }
+    }
+
+    fn test_log() -> (TempDir, AttendanceLog) {
+        let dir = tempdir().unwrap();
+        let log = AttendanceLog::new(&dir.path().join(FILE_NAME));
+        (dir, log)
+    }
+
+    #[test]
+    fn records_are_returned_for_the_days_in_range() {
+        let (_dir, log) = test_log();
+        // 00:30 in UTC+9 is the previous day in UTC
+        let records = [
+            record(1, 23, "00123"),
+            record(2, 0, "00123"),
+            record(3, 12, "00124"),
+            record(4, 9, "00123"),
+        ];
+        for record in &records {
+            log.append(record).unwrap();
+        }
+
+        let queried = log.query(date(2)..=date(3), None).unwrap();
+
+        assert_eq!(queried, records[1..3]);
+    }
+
+    #[test]
+    fn records_are_filtered_by_student() {
+        let (_dir, log) = test_log();
+        let records = [
+            record(1, 9, "00123"),
+            record(1, 10, "00124"),
+            record(2, 9, "00123"),
+        ];
+        for record in &records {
+            log.append(record).unwrap();
+        }
+
+        let queried = log.query(date(1)..=date(2), Some("00123")).unwrap();
+
+        assert_eq!(queried, [records[0].clone(), records[2].clone()]);
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let (_dir, log) = test_log();
+        log.append(&record(1, 9, "00123")).unwrap();
+        fs::write(
+            &log.path,
+            format!("{}not a record\n\n", fs::read_to_string(&log.path).unwrap()),
+        )
+        .unwrap();
+        log.append(&record(1, 10, "00124")).unwrap();
+
+        let queried = log.query(date(1)..=date(1), None).unwrap();
+
+        assert_eq!(queried, [record(1, 9, "00123"), record(1, 10, "00124")]);
+    }
+
+    #[test]
+    fn missing_log_has_no_records() {
+        let (_dir, log) = test_log();
+
+        let queried = log.query(Date::MIN..=Date::MAX, None).unwrap();
+
+        assert!(queried.is_empty());
+    }
+}
@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Represents errors that can occur in the `attendance` module.
+///
+/// # Variants
+///
+/// * `Io` - Represents I/O errors that occur while reading or appending to the log file.
+/// * `Json` - Represents errors when serializing a record to JSON.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Represents I/O errors that occur during file operations.
+    ///
+    /// This variant wraps `std::io::Error` and is used when the attendance log cannot be read
+    /// or appended to.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// A JSON serialization error, originating from the `serde_json` library.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
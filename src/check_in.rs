@@ -0,0 +1,324 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use time::OffsetDateTime;
+
+use crate::attendance::{AttendanceLog, Outcome, Record};
+use crate::my_studio::HttpClient;
+use crate::offline_queue::{OfflineQueue, QueuedCheckIn, Replayer};
+use crate::spreadsheet::{Id, Student};
+
+/// The front desk of the studio, where students sign in.
+///
+/// This struct ties together everything a sign-in touches: the roster used to resolve IDs, the
+/// MyStudio client, the offline queue used while the API is unreachable and the local attendance
+/// log. It is cheap to clone, and all clones share the same state.
+///
+/// # Fields
+///
+/// * `roster` - The students that can sign in, keyed by ID.
+/// * `client` - The client used to record check-ins in MyStudio.
+/// * `offline_queue` - Check-ins waiting to be sent to MyStudio.
+/// * `replayer` - Sends the queued check-ins once MyStudio is reachable again.
+/// * `log` - The local record of every sign-in attempt.
+#[derive(Debug, Clone)]
+pub struct Desk {
+    roster: Rc<RefCell<HashMap<Id, Student>>>,
+    client: HttpClient,
+    offline_queue: Rc<RefCell<OfflineQueue>>,
+    replayer: Replayer,
+    log: AttendanceLog,
+}
+
+impl Desk {
+    pub fn new(
+        roster: Rc<RefCell<HashMap<Id, Student>>>,
+        client: HttpClient,
+        offline_queue: Rc<RefCell<OfflineQueue>>,
+        replayer: Replayer,
+        log: AttendanceLog,
+    ) -> Self {
+        Self {
+            roster,
+            client,
+            offline_queue,
+            replayer,
+            log,
+        }
+    }
+
+    /// Returns the students that can sign in, keyed by ID.
+    pub fn roster(&self) -> &Rc<RefCell<HashMap<Id, Student>>> {
+        &self.roster
+    }
+
+    /// Returns the attendance log that sign-in attempts are recorded in.
+    pub fn log(&self) -> &AttendanceLog {
+        &self.log
+    }
+
+    /// Looks up the student with the given ID in the roster.
+    pub fn find(&self, student_id: &str) -> Option<Student> {
+        self.roster.try_borrow().unwrap().get(student_id).cloned()
+    }
+
+    /// Finds the students whose name matches a partial name.
+    ///
+    /// Every word of `query` has to match the name, ignoring case. A word matches best at the
+    /// start of a word of the name, less well anywhere in the name and least well when its letters
+    /// only appear in order, e.g. `"jsmth"` in `"John Smith"`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The partial name typed by the student.
+    /// * `limit` - The maximum number of students returned.
+    ///
+    /// # Returns
+    ///
+    /// Returns the matching students with their IDs, best matches first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(Id, Student)> {
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let roster = self.roster.try_borrow().unwrap();
+        let mut matches: Vec<(u32, &Id, &Student)> = roster
+            .iter()
+            .filter_map(|(id, student)| {
+                let name = student.name.to_lowercase();
+                words
+                    .iter()
+                    .map(|word| match_score(&name, word))
+                    .sum::<Option<u32>>()
+                    .map(|score| (score, id, student))
+            })
+            .collect();
+        matches.sort_by(|(a_score, _, a), (b_score, _, b)| {
+            b_score.cmp(a_score).then_with(|| a.name.cmp(&b.name))
+        });
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, id, student)| (id.clone(), student.clone()))
+            .collect()
+    }
+
+    /// Signs in the student with the given ID.
+    ///
+    /// The ID is resolved against the roster and, if found, the check-in is sent to MyStudio with
+    /// the ID as written in the roster. If MyStudio cannot be reached, the check-in is queued
+    /// instead. A check-in that reached MyStudio without being confirmed is not queued, since
+    /// sending it again could record it twice. Every attempt, including unknown IDs, is appended
+    /// to the attendance log.
+    ///
+    /// # Arguments
+    ///
+    /// * `student_id` - The normalized ID the student is looked up by.
+    ///
+    /// # Returns
+    ///
+    /// Returns the record that was logged for this attempt.
+    pub async fn check_in(&self, student_id: &str) -> Record {
+        let timestamp = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let student = self.find(student_id);
+
+        let outcome = match &student {
+            Some(student) => self.send(student, timestamp).await,
+            None => Outcome::UnknownId,
+        };
+
+        let record = Record {
+            timestamp,
+            student_id: student
+                .as_ref()
+                .map_or(student_id, |student| &student.id)
+                .to_owned(),
+            student_name: student.map(|student| student.name),
+            outcome,
+        };
+        if let Err(e) = self.log.append(&record) {
+            eprintln!(
+                "Error when logging check-in of '{}': {e}",
+                record.student_id
+            );
+        }
+
+        record
+    }
+
+    async fn send(&self, student: &Student, recorded_at: OffsetDateTime) -> Outcome {
+        let student_id = &student.id;
+        match self.client.check_in(student_id).await {
+            Ok(_) => {
+                // the API is reachable again, so this is a good time to catch up
+                if !self.offline_queue.borrow().is_empty() {
+                    self.replayer.replay();
+                }
+                Outcome::CheckedIn
+            }
+            Err(e) if e.is_unreachable() => {
+                eprintln!("MyStudio is unreachable, queueing check-in of '{student_id}': {e}");
+                let queued = self.offline_queue.borrow_mut().push(QueuedCheckIn {
+                    student_id: student_id.clone(),
+                    student_name: student.name.clone(),
+                    recorded_at,
+                });
+                match queued {
+                    Ok(()) => Outcome::Queued,
+                    Err(e) => {
+                        eprintln!("Error when queueing check-in of '{student_id}': {e}");
+                        Outcome::Failed {
+                            error: e.to_string(),
+                        }
+                    }
+                }
+            }
+            Err(e) if e.is_unconfirmed() => {
+                eprintln!(
+                    "MyStudio did not confirm the check-in of '{student_id}', it may or may not \
+                     have been recorded: {e}"
+                );
+                Outcome::Unconfirmed {
+                    error: e.to_string(),
+                }
+            }
+            Err(e) => {
+                eprintln!("Error when checking in student '{student_id}': {e}");
+                Outcome::Failed {
+                    error: e.to_string(),
+                }
+            }
+        }
+    }
+}
+
+/// Scores how well a word of a search matches a lowercase name.
+///
+/// # Returns
+///
+/// Returns `None` if the word does not match, or a higher score for a better match.
+fn match_score(name: &str, word: &str) -> Option<u32> {
+    if name.split_whitespace().any(|part| part.starts_with(word)) {
+        Some(3)
+    } else if name.contains(word) {
+        Some(2)
+    } else {
+        let mut letters = name.chars();
+        word.chars()
+            .all(|c| letters.any(|letter| letter == c))
+            .then_some(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::my_studio::stub;
+    use crate::spreadsheet;
+
+    #[test]
+    fn word_at_the_start_of_a_name_part_scores_highest() {
+        assert_eq!(match_score("ada lovelace", "love"), Some(3));
+        assert_eq!(match_score("ada lovelace", "ada"), Some(3));
+    }
+
+    #[test]
+    fn word_inside_a_name_scores_below_a_prefix() {
+        assert_eq!(match_score("ada lovelace", "lace"), Some(2));
+        assert_eq!(match_score("ada lovelace", "a l"), Some(2));
+    }
+
+    #[test]
+    fn letters_in_order_score_lowest() {
+        assert_eq!(match_score("ada lovelace", "alc"), Some(1));
+        assert_eq!(match_score("ada lovelace", "dll"), Some(1));
+    }
+
+    #[test]
+    fn letters_out_of_order_do_not_match() {
+        assert_eq!(match_score("ada lovelace", "cla"), None);
+        assert_eq!(match_score("ada lovelace", "x"), None);
+    }
+
+    #[tokio::test]
+    async fn zero_padded_id_is_sent_as_written_in_roster() {
+        let dir = tempdir().unwrap();
+        let roster_path = dir.path().join("roster.csv");
+        fs::write(&roster_path, "0042,Ada Lovelace\n").unwrap();
+
+        let mut config = Config::default();
+        config.student_data.filepath = roster_path;
+        config.student_data.name_column = spreadsheet::Column::Index(1);
+        config.student_data.id_normalization.strip_leading_zeros = true;
+        let (roster, _) = spreadsheet::load_student_info(&config).unwrap();
+        let typed_id = config.student_data.id_normalization.normalize("42");
+
+        let (api_url, mut requests) =
+            stub::start(|_| (200, json!({ "status": "Success", "msg": "ok" }).to_string())).await;
+
+        let desk = Desk::new(
+            Rc::new(RefCell::new(roster)),
+            HttpClient::with_api_url(Rc::new(RefCell::new(config)), &api_url),
+            Rc::new(RefCell::new(
+                OfflineQueue::open(&dir.path().join("offline_queue.jsonl")).unwrap(),
+            )),
+            Replayer::detached(),
+            AttendanceLog::new(&dir.path().join("attendance.jsonl")),
+        );
+        let record = desk.check_in(&typed_id).await;
+
+        assert_eq!(record.outcome, Outcome::CheckedIn);
+        assert_eq!(record.student_id, "0042");
+        // the first request acquires the session token
+        requests.recv().await.unwrap();
+        assert_eq!(requests.recv().await.unwrap().body["student_id"], "0042");
+    }
+
+    #[tokio::test]
+    async fn server_error_is_unconfirmed_instead_of_queued() {
+        let dir = tempdir().unwrap();
+        let roster_path = dir.path().join("roster.csv");
+        fs::write(&roster_path, "7,Grace Hopper\n").unwrap();
+
+        let mut config = Config::default();
+        config.student_data.filepath = roster_path;
+        config.student_data.name_column = spreadsheet::Column::Index(1);
+        let (roster, _) = spreadsheet::load_student_info(&config).unwrap();
+
+        let (api_url, _requests) = stub::start(|request| {
+            if request.path == "/studioAttendanceCheckin" {
+                (502, String::new())
+            } else {
+                (
+                    200,
+                    json!({ "status": "Success", "msg": "token" }).to_string(),
+                )
+            }
+        })
+        .await;
+
+        let offline_queue = Rc::new(RefCell::new(
+            OfflineQueue::open(&dir.path().join("offline_queue.jsonl")).unwrap(),
+        ));
+        let desk = Desk::new(
+            Rc::new(RefCell::new(roster)),
+            HttpClient::with_api_url(Rc::new(RefCell::new(config)), &api_url),
+            Rc::clone(&offline_queue),
+            Replayer::detached(),
+            AttendanceLog::new(&dir.path().join("attendance.jsonl")),
+        );
+        let record = desk.check_in("7").await;
+
+        assert!(matches!(record.outcome, Outcome::Unconfirmed { .. }));
+        // sending it again could record the student twice
+        assert!(offline_queue.borrow().is_empty());
+    }
+}
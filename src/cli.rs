@@ -0,0 +1,222 @@
+mod error;
+
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use clap::{Args, Parser, Subcommand};
+pub use error::Error;
+use error::Result;
+use time::{Date, OffsetDateTime};
+use tokio::runtime::Runtime;
+
+use crate::attendance::{self, AttendanceLog, Outcome, Record};
+use crate::config::Config;
+use crate::export;
+use crate::my_studio::HttpClient;
+use crate::spreadsheet;
+
+/// A welcome kiosk that signs students in to MyStudio.
+///
+/// Without a subcommand, the kiosk window is opened.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// The configuration file to use. It is created with default settings if it does not exist.
+    ///
+    /// Defaults to the path in YOUKOSO_CONFIG, then `youkoso/config.toml` in the user's config
+    /// directory, then `config.toml` next to the executable.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Open the kiosk window. This is the default.
+    Run,
+
+    /// Sign in a student without opening the kiosk window.
+    CheckIn(CheckInArgs),
+
+    /// Check or list the students in the roster spreadsheet.
+    #[command(subcommand)]
+    Roster(RosterCommand),
+
+    /// Troubleshoot the MyStudio session token.
+    #[command(subcommand)]
+    Token(TokenCommand),
+
+    /// Export the local attendance log to a CSV or Excel file.
+    Export(ExportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CheckInArgs {
+    /// The ID of the student, as it would be entered at the kiosk.
+    pub id: String,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RosterCommand {
+    /// Load the roster and report the problems found in it.
+    Validate,
+
+    /// List the students in the roster.
+    Show,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TokenCommand {
+    /// Request a session token from MyStudio with the configured credentials.
+    Test,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// The first day to export, as YYYY-MM-DD. Defaults to today.
+    #[arg(long, value_parser = export::parse_date)]
+    pub from: Option<Date>,
+
+    /// The last day to export, as YYYY-MM-DD. Defaults to the first day.
+    #[arg(long, value_parser = export::parse_date)]
+    pub to: Option<Date>,
+
+    /// Only export sign-ins of the student with this ID.
+    #[arg(long)]
+    pub student: Option<String>,
+
+    /// The file to write. The format is chosen by its extension, `.csv` or `.xlsx`.
+    pub output: PathBuf,
+}
+
+/// Exports the sign-ins in the attendance log as described by `args`.
+///
+/// # Returns
+///
+/// Returns the number of exported sign-ins.
+pub fn export(config: &Config, args: &ExportArgs) -> Result<usize> {
+    let today = OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+        .date();
+    let from = args.from.unwrap_or(today);
+    let to = args.to.unwrap_or(from);
+
+    Ok(export::export(
+        config,
+        from,
+        to,
+        args.student.as_deref(),
+        &args.output,
+    )?)
+}
+
+/// Signs in the student with the given ID and records the attempt in the attendance log.
+///
+/// Unlike at the kiosk, a check-in that cannot reach MyStudio is not queued, so that the command
+/// can be used to find out whether MyStudio is reachable.
+///
+/// # Returns
+///
+/// Returns the record that was logged for this attempt.
+///
+/// # Errors
+///
+/// Returns an error if the roster cannot be loaded, if the ID is not in the roster or if MyStudio
+/// does not record the check-in.
+pub fn check_in(config: &Rc<RefCell<Config>>, args: &CheckInArgs) -> Result<Record> {
+    let timestamp = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    let (student, student_id, log) = {
+        let config = config.try_borrow().unwrap();
+        let student_id = config.student_data.id_normalization.normalize(&args.id);
+        let (mut roster, _) = spreadsheet::load_student_info(&config)?;
+        let log = AttendanceLog::new(&config.path().with_file_name(attendance::FILE_NAME));
+        (roster.remove(&student_id), student_id, log)
+    };
+
+    let Some(student) = student else {
+        log.append(&Record {
+            timestamp,
+            student_id: student_id.clone(),
+            student_name: None,
+            outcome: Outcome::UnknownId,
+        })?;
+        return Err(Error::UnknownId { id: student_id });
+    };
+
+    let client = HttpClient::new(Rc::clone(config));
+    let checked_in = Runtime::new()?.block_on(client.check_in(&student.id));
+    let record = Record {
+        timestamp,
+        student_id: student.id,
+        student_name: Some(student.name),
+        outcome: match &checked_in {
+            Ok(_) => Outcome::CheckedIn,
+            Err(e) if e.is_unconfirmed() => Outcome::Unconfirmed {
+                error: e.to_string(),
+            },
+            Err(e) => Outcome::Failed {
+                error: e.to_string(),
+            },
+        },
+    };
+    log.append(&record)?;
+    checked_in?;
+
+    Ok(record)
+}
+
+/// Loads the roster and prints the problems found in it.
+///
+/// # Errors
+///
+/// Returns an error if the roster cannot be loaded, or `Error::InvalidRoster` if it loaded with
+/// problems, so that scripts can tell from the exit status.
+pub fn validate_roster(config: &Config) -> Result<()> {
+    let (roster, report) = spreadsheet::load_student_info(config)?;
+    println!("{report}");
+    println!("{} students loaded", roster.len());
+
+    if report.has_problems() {
+        return Err(Error::InvalidRoster);
+    }
+    Ok(())
+}
+
+/// Prints the students in the roster as tab-separated rows, in spreadsheet order.
+///
+/// # Errors
+///
+/// Returns an error if the roster cannot be loaded.
+pub fn show_roster(config: &Config) -> Result<()> {
+    let (roster, _) = spreadsheet::load_student_info(config)?;
+    let mut students: Vec<_> = roster.values().collect();
+    students.sort_by_key(|student| student.row);
+
+    println!("Row\tID\tName\tImmediate sign-in");
+    for student in students {
+        println!(
+            "{}\t{}\t{}\t{}",
+            student.row,
+            student.id,
+            student.name,
+            if student.immediate_sign_in {
+                "yes"
+            } else {
+                "no"
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Requests a session token from MyStudio, to check the configured credentials.
+///
+/// # Errors
+///
+/// Returns an error if MyStudio cannot be reached or does not grant a token.
+pub fn test_token(config: &Rc<RefCell<Config>>) -> Result<()> {
+    let client = HttpClient::new(Rc::clone(config));
+    Runtime::new()?.block_on(client.aquire_session_token())?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Represents errors that can occur while running a command-line subcommand.
+///
+/// # Variants
+///
+/// * `Attendance` - Represents errors when reading the attendance log.
+/// * `Credentials` - Represents errors when reading the credentials from the secret store.
+/// * `Export` - Represents errors when writing an export file.
+/// * `Io` - Represents I/O errors, such as when the async runtime cannot be started.
+/// * `MyStudio` - Represents errors when talking to the MyStudio API.
+/// * `Spreadsheet` - Represents errors when loading the roster.
+/// * `UnknownId` - Indicates that an ID is not in the roster.
+/// * `InvalidRoster` - Indicates that the roster loaded, but with problems.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// An error when reading the attendance log, originating from the `attendance` module.
+    #[error(transparent)]
+    Attendance(#[from] crate::attendance::Error),
+
+    /// An error when reading the credentials, originating from the `config` module.
+    #[error("Cannot load the credentials: {0}")]
+    Credentials(#[from] crate::config::Error),
+
+    /// An error when writing an export file, originating from the `export` module.
+    #[error(transparent)]
+    Export(#[from] crate::export::Error),
+
+    /// Represents I/O errors, such as when the async runtime cannot be started.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// An error when talking to the MyStudio API, originating from the `my_studio` module.
+    #[error(transparent)]
+    MyStudio(#[from] crate::my_studio::Error),
+
+    /// An error when loading the roster, originating from the `spreadsheet` module.
+    #[error(transparent)]
+    Spreadsheet(#[from] crate::spreadsheet::Error),
+
+    /// Indicates that an ID is not in the roster, so MyStudio was not contacted.
+    #[error("The ID '{id}' is not in the roster.")]
+    UnknownId { id: String },
+
+    /// Indicates that the roster has problems, such as duplicate IDs or rows without an ID.
+    #[error("The roster has problems.")]
+    InvalidRoster,
+}
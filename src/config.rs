@@ -1,14 +1,47 @@
 mod error;
+mod lenient;
+mod migration;
+mod overrides;
 
 use std::{
-    fs,
+    env,
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    mem,
     path::{Path, PathBuf},
+    rc::Rc,
 };
 
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
+};
+pub use error::{Error, Warning};
 use error::{Result, TomlError};
+pub use migration::CURRENT_VERSION;
+pub use overrides::Overrides;
 use serde::{Deserialize, Serialize};
+use time::{Date, Month};
+use toml::Table;
+
+use crate::secrets::{self, SecretHandle, SecretStore};
+use crate::spreadsheet::Column;
+
+/// The name of the configuration file when it is not given explicitly.
+pub const FILE_NAME: &str = "config.toml";
+/// The environment variable the path of the configuration file can be given in.
+pub const PATH_VAR: &str = "YOUKOSO_CONFIG";
+/// The directory in the user's config directory the configuration file is kept in.
+const APP_DIR_NAME: &str = "youkoso";
+/// The key of the MyStudio email, which is kept in the secret store unless it is overridden.
+const MY_STUDIO_EMAIL_KEY: &str = "my_studio.email";
+/// How many earlier versions of the configuration file are kept when it is saved.
+pub const BACKUP_COUNT: usize = 5;
 
-use crate::spreadsheet::ColumnIndex;
+/// The fewest characters an admin PIN can have.
+pub const MIN_ADMIN_PIN_LENGTH: usize = 4;
 
 /// Configuration for the application.
 ///
@@ -18,17 +51,51 @@ use crate::spreadsheet::ColumnIndex;
 ///
 /// # Fields
 ///
+/// * `version` - The version of the configuration format, used to upgrade older files.
 /// * `theme` - The theme setting for the application's user interface.
 /// * `my_studio` - Authentication credentials and settings for the MyStudio API.
+/// * `student_data` - Where the roster is read from and how.
+/// * `kiosk` - What students can do on the home page.
+/// * `secrets` - Where credentials are stored instead of this file.
 /// * `config_path` - The path to the configuration file (not serialized to TOML).
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// * `secret_store` - The store credentials are read from and saved to (not serialized to TOML).
+/// * `warnings` - The problems found in the file when it was loaded (not serialized to TOML).
+/// * `overrides` - The settings replaced by environment variables (not serialized to TOML).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    pub version: u32,
     pub theme: Theme,
     pub my_studio: MyStudio,
     pub student_data: StudentData,
+    pub kiosk: Kiosk,
+    pub secrets: Secrets,
 
     #[serde(skip)]
     config_path: PathBuf,
+    #[serde(skip)]
+    secret_store: SecretHandle,
+    #[serde(skip)]
+    warnings: Vec<Warning>,
+    #[serde(skip)]
+    overrides: Overrides,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            theme: Default::default(),
+            my_studio: Default::default(),
+            student_data: Default::default(),
+            kiosk: Default::default(),
+            secrets: Default::default(),
+            config_path: Default::default(),
+            secret_store: Default::default(),
+            warnings: Default::default(),
+            overrides: Default::default(),
+        }
+    }
 }
 
 /// Application user interface theme options.
@@ -56,14 +123,47 @@ pub enum Theme {
 ///
 /// # Fields
 ///
-/// * `email` - The user's email address used for authentication.
+/// * `email` - The user's email address used for authentication. It is kept in the secret store
+///   and never written to the configuration file, so it is only kept in memory while no secret
+///   store is available. An email found in the file is moved to the secret store once one is
+///   attached.
 /// * `company_id` - The identifier for the user's company within the MyStudio system.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MyStudio {
+    #[serde(skip_serializing)]
     pub email: String,
     pub company_id: String,
 }
 
+/// Settings for storing credentials.
+///
+/// # Fields
+///
+/// * `backend` - Which secret store credentials are kept in.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Secrets {
+    pub backend: SecretBackend,
+}
+
+/// The secret stores credentials can be kept in.
+///
+/// # Variants
+///
+/// * `Auto` - The Secret Service if one is running, the encrypted file otherwise (default).
+/// * `SecretService` - The Linux Secret Service, such as GNOME Keyring or KWallet.
+/// * `File` - A file next to the configuration file, encrypted with the passphrase in
+///   `YOUKOSO_SECRETS_PASSPHRASE`. This works on headless machines. Without the variable, the
+///   passphrase is kept in a file next to it, so the credentials are only obfuscated.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SecretBackend {
+    #[default]
+    Auto,
+    SecretService,
+    File,
+}
+
 /// Configuration for student data management.
 ///
 /// This struct contains settings related to the source and structure of student data,
@@ -73,16 +173,25 @@ pub struct MyStudio {
 ///
 /// * `filepath` - Path to the file containing student data.
 /// * `sheet_name` - Name of the worksheet containing student records.
-/// * `name_column` - Index of the column containing student names.
-/// * `id_column` - Index of the column containing student identifiers.
+/// * `name_column` - The column containing student names, by index or by header.
+/// * `id_column` - The column containing student identifiers, by index or by header.
+/// * `delimiter` - The field delimiter of CSV and TSV files. Defaults to a comma for `.csv` files
+///   and a tab for `.tsv` files.
+/// * `encoding` - The character encoding of CSV and TSV files, e.g. `"Shift_JIS"`. Defaults to
+///   UTF-8.
 /// * `immediate_sign_in` - Configuration for automatic sign-in functionality.
+/// * `id_normalization` - How IDs are cleaned up before the roster and typed IDs are compared.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StudentData {
     pub filepath: PathBuf,
     pub sheet_name: String,
-    pub name_column: ColumnIndex,
-    pub id_column: ColumnIndex,
+    pub name_column: Column,
+    pub id_column: Column,
+    pub delimiter: Option<char>,
+    pub encoding: Option<String>,
     pub immediate_sign_in: ImmediateSignIn,
+    pub id_normalization: IdNormalization,
 }
 
 /// Configuration for immediate sign-in functionality.
@@ -93,12 +202,17 @@ pub struct StudentData {
 ///
 /// # Fields
 ///
-/// * `column` - Index of the column that indicates immediate sign-in eligibility.
+/// * `column` - The column that indicates immediate sign-in eligibility, by index or by header. If
+///   its header is not found, the roster still loads and no student signs in immediately.
 /// * `enabled_symbol` - The string value that, when present in the column, enables immediate sign-in.
+///   Several values can be given separated by commas, e.g. `"TRUE, Y, x"`.
+/// * `case_sensitive` - Whether the column must match the case of `enabled_symbol` exactly.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ImmediateSignIn {
-    pub column: ColumnIndex,
+    pub column: Column,
     pub enabled_symbol: String,
+    pub case_sensitive: bool,
 }
 
 impl Default for ImmediateSignIn {
@@ -106,16 +220,387 @@ impl Default for ImmediateSignIn {
         Self {
             column: Default::default(),
             enabled_symbol: "TRUE".to_owned(),
+            case_sensitive: false,
+        }
+    }
+}
+
+impl ImmediateSignIn {
+    /// Returns the values that enable immediate sign-in, as listed in `enabled_symbol`.
+    ///
+    /// Surrounding whitespace is ignored and empty entries are skipped.
+    fn enabled_symbols(&self) -> impl Iterator<Item = &str> {
+        self.enabled_symbol
+            .split(',')
+            .map(str::trim)
+            .filter(|symbol| !symbol.is_empty())
+    }
+
+    /// Checks whether a cell value enables immediate sign-in.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The text of the cell in the immediate sign-in column.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if `value`, ignoring surrounding whitespace, equals one of the enabled
+    /// symbols. Case is ignored unless `case_sensitive` is set.
+    pub fn is_enabled(&self, value: &str) -> bool {
+        let value = value.trim();
+        self.enabled_symbols().any(|symbol| {
+            if self.case_sensitive {
+                symbol == value
+            } else {
+                symbol.to_lowercase() == value.to_lowercase()
+            }
+        })
+    }
+}
+
+/// Rules that turn an ID into the form it is looked up by.
+///
+/// The same rules are applied to the IDs read from the roster and to the IDs typed on the home
+/// page, so that e.g. `" 0042"` typed by a student finds `42` in the spreadsheet.
+///
+/// # Fields
+///
+/// * `trim` - Whether surrounding whitespace is removed.
+/// * `case` - How letters are cased.
+/// * `strip_leading_zeros` - Whether leading zeros are removed. An ID made only of zeros keeps a
+///   single `0`.
+/// * `integer_floats` - Whether numeric cells holding a whole number are written without a
+///   fractional part, even when the spreadsheet stored them with a rounding error.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdNormalization {
+    pub trim: bool,
+    pub case: IdCase,
+    pub strip_leading_zeros: bool,
+    pub integer_floats: bool,
+}
+
+/// How the letters of an ID are cased before it is looked up.
+///
+/// # Variants
+///
+/// * `Preserve` - Letters are kept as they are, so IDs are matched case-sensitively (default).
+/// * `Lower` - Letters are converted to lowercase.
+/// * `Upper` - Letters are converted to uppercase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IdCase {
+    #[default]
+    Preserve,
+    Lower,
+    Upper,
+}
+
+impl Default for IdNormalization {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            case: IdCase::Preserve,
+            strip_leading_zeros: false,
+            integer_floats: true,
+        }
+    }
+}
+
+impl IdNormalization {
+    /// Applies the rules to an ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID as typed or as read from the roster.
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID in the form it is looked up by.
+    pub fn normalize(&self, id: &str) -> String {
+        let id = if self.trim { id.trim() } else { id };
+        let id = if self.strip_leading_zeros && id.starts_with('0') {
+            match id.trim_start_matches('0') {
+                "" => "0",
+                stripped => stripped,
+            }
+        } else {
+            id
+        };
+
+        match self.case {
+            IdCase::Preserve => id.to_owned(),
+            IdCase::Lower => id.to_lowercase(),
+            IdCase::Upper => id.to_uppercase(),
+        }
+    }
+}
+
+/// Settings for the home page, where students sign in.
+///
+/// # Fields
+///
+/// * `name_search` - Whether students can find themselves by name instead of typing their ID.
+/// * `scanner` - Settings for signing in with a membership card scanner.
+/// * `greeting` - Settings for the screen that welcomes students once they are signed in.
+/// * `lockdown` - Whether the window is kept fullscreen, cannot be closed and hides the way to the
+///   settings page. Requires an admin PIN to be set.
+/// * `admin_pin_hash` - The Argon2 hash of the PIN that protects the settings page, or `None` if
+///   the settings page is not protected.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Kiosk {
+    pub name_search: bool,
+    pub scanner: Scanner,
+    pub greeting: Greeting,
+    pub lockdown: bool,
+    pub admin_pin_hash: Option<String>,
+}
+
+impl Kiosk {
+    /// Returns whether the settings page is protected by an admin PIN.
+    pub fn has_admin_pin(&self) -> bool {
+        self.admin_pin_hash.is_some()
+    }
+
+    /// Protects the settings page with a new admin PIN. Only a salted hash of the PIN is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::PinTooShort` if the PIN has fewer than `MIN_ADMIN_PIN_LENGTH` characters,
+    /// or `Error::PinHash` if it cannot be hashed.
+    pub fn set_admin_pin(&mut self, pin: &str) -> Result<()> {
+        if pin.chars().count() < MIN_ADMIN_PIN_LENGTH {
+            return Err(Error::PinTooShort {
+                min_length: MIN_ADMIN_PIN_LENGTH,
+            });
+        }
+
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(pin.as_bytes(), &salt)
+            .map_err(Error::PinHash)?;
+        self.admin_pin_hash = Some(hash.to_string());
+
+        Ok(())
+    }
+
+    /// Checks a PIN against the admin PIN.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if no admin PIN is set or `pin` matches it.
+    pub fn verify_admin_pin(&self, pin: &str) -> bool {
+        let Some(hash) = &self.admin_pin_hash else {
+            return true;
+        };
+
+        match PasswordHash::new(hash) {
+            Ok(hash) => Argon2::default()
+                .verify_password(pin.as_bytes(), &hash)
+                .is_ok(),
+            Err(e) => {
+                eprintln!("Error when reading the admin PIN hash: {e}");
+                false
+            }
         }
     }
 }
 
+/// Settings for barcode and QR scanners that act as a keyboard.
+///
+/// # Fields
+///
+/// * `enabled` - Whether fast input ending in Enter is recognised as a scan and submitted, even
+///   when the ID field does not have focus.
+/// * `prefix` - Text the scanner adds before each code, removed before the code is submitted.
+/// * `suffix` - Text the scanner adds after each code, removed before the code is submitted. The
+///   Enter that ends a scan is not part of it, and neither are other control characters.
+/// * `hide_id_field` - Whether the ID field is hidden while the scanner is enabled, so that
+///   students can only sign in by scanning their card.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scanner {
+    pub enabled: bool,
+    pub prefix: String,
+    pub suffix: String,
+    pub hide_id_field: bool,
+}
+
+/// Settings for the screen that welcomes students once they are signed in.
+///
+/// # Fields
+///
+/// * `duration_secs` - How many seconds the greeting is shown. `0` turns the greeting off.
+/// * `messages` - Extra messages shown to particular students, e.g. on their birthday. These can
+///   only be edited in the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Greeting {
+    pub duration_secs: u64,
+    pub messages: Vec<GreetingMessage>,
+}
+
+impl Default for Greeting {
+    fn default() -> Self {
+        Self {
+            duration_secs: 3,
+            messages: Vec::new(),
+        }
+    }
+}
+
+impl Greeting {
+    /// Finds the extra message for a student.
+    ///
+    /// # Arguments
+    ///
+    /// * `student_id` - The ID of the student being greeted.
+    /// * `today` - The current date, used to match messages limited to a day.
+    ///
+    /// # Returns
+    ///
+    /// Returns the first message for the student that applies today, preferring messages limited
+    /// to today over ones shown every day.
+    pub fn message_for(&self, student_id: &str, today: Date) -> Option<&str> {
+        let today = format!("{:02}-{:02}", u8::from(today.month()), today.day());
+        let messages = || {
+            self.messages
+                .iter()
+                .filter(move |message| message.student_id == student_id)
+        };
+
+        messages()
+            .find(|message| message.day.as_deref() == Some(today.as_str()))
+            .or_else(|| messages().find(|message| message.day.is_none()))
+            .map(|message| message.text.as_str())
+    }
+}
+
+/// An extra message shown to a student when they are greeted.
+///
+/// # Fields
+///
+/// * `student_id` - The ID of the student, as found in the roster.
+/// * `text` - The message, e.g. `"Happy birthday!"`.
+/// * `day` - The day of the year the message is shown on, written as `MM-DD`. Without it, the
+///   message is shown every day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawGreetingMessage")]
+pub struct GreetingMessage {
+    pub student_id: String,
+    pub text: String,
+    pub day: Option<String>,
+}
+
+/// A `GreetingMessage` as written in the configuration file, before its day is checked.
+#[derive(Deserialize)]
+struct RawGreetingMessage {
+    student_id: String,
+    text: String,
+    #[serde(default)]
+    day: Option<String>,
+}
+
+impl TryFrom<RawGreetingMessage> for GreetingMessage {
+    type Error = String;
+
+    /// Checks that the day is a day of the year and writes it as `MM-DD`.
+    ///
+    /// A message with an invalid day is rejected as a whole rather than shown every day, so that
+    /// e.g. a birthday message is never shown on the wrong days.
+    fn try_from(raw: RawGreetingMessage) -> std::result::Result<Self, Self::Error> {
+        let day = raw
+            .day
+            .map(|day| {
+                parse_day(&day).ok_or_else(|| format!("'{day}' is not a day written as MM-DD"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            student_id: raw.student_id,
+            text: raw.text,
+            day,
+        })
+    }
+}
+
+/// Parses a day of the year written as `MM-DD`, returning it with both parts zero-padded.
+///
+/// February 29 is accepted, as it is a day of the year in leap years.
+fn parse_day(day: &str) -> Option<String> {
+    let (month, day) = day.trim().split_once('-')?;
+    let month = Month::try_from(month.trim().parse::<u8>().ok()?).ok()?;
+    let day = day.trim().parse::<u8>().ok()?;
+    // 2000 is a leap year, so every day of the year exists in it
+    Date::from_calendar_date(2000, month, day).ok()?;
+
+    Some(format!("{:02}-{day:02}", u8::from(month)))
+}
+
 impl Config {
+    /// Returns the path of the file this configuration was loaded from and is saved to.
+    ///
+    /// Other persistent application data, such as the offline check-in queue, is stored next to
+    /// this file.
+    pub fn path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// Returns the problems found in the configuration file when it was loaded.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Returns the settings replaced by environment variables.
+    pub fn overrides(&self) -> &Overrides {
+        &self.overrides
+    }
+
+    /// Returns the store credentials are kept in, if one has been attached.
+    pub fn secret_store(&self) -> Option<&dyn SecretStore> {
+        self.secret_store.store()
+    }
+
+    /// Attaches the store credentials are kept in and reads them from it.
+    ///
+    /// Credentials that were read from the configuration file, as written by older versions, are
+    /// moved to the store and the file is saved without them. Credentials overridden by an
+    /// environment variable are left alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Secrets` if the store cannot be read or written, or any error of `save`.
+    pub fn attach_secret_store(&mut self, store: Rc<dyn SecretStore>) -> Result<()> {
+        self.secret_store = SecretHandle::new(store);
+
+        if self.overrides.variable(MY_STUDIO_EMAIL_KEY).is_some() {
+            return Ok(());
+        }
+        if self.my_studio.email.is_empty() {
+            self.my_studio.email = self.secret_store.get(secrets::MY_STUDIO_EMAIL)?;
+        } else {
+            self.save()?;
+            eprintln!(
+                "Moved the MyStudio email from '{}' to the {} secret store",
+                self.config_path.display(),
+                self.secret_store.store().unwrap().name()
+            );
+        }
+
+        Ok(())
+    }
+
     /// Saves the current configuration to its associated file.
     ///
     /// This method serializes the configuration to TOML format using pretty-printing,
     /// and writes it to the path stored in `config_path`. The method handles both
-    /// the serialization to TOML and writing the file to disk.
+    /// the serialization to TOML and writing the file to disk. Credentials are written to the
+    /// attached secret store instead and never to the file. Without a secret store they are only
+    /// kept in memory, see `has_unsaved_credentials`.
+    ///
+    /// The file is replaced atomically, so that a crash while saving cannot leave it half written,
+    /// and the previous file is kept as a backup, see `restore_backup`. Nothing is written if the
+    /// file already holds this configuration.
     ///
     /// # Returns
     ///
@@ -126,22 +611,271 @@ impl Config {
     ///
     /// This method can return the following errors:
     /// - `Error::Toml` if the configuration cannot be serialized to TOML.
-    /// - `Error::Io` if the file cannot be written to disk.
+    /// - `Error::Io` if the file or its backups cannot be written to disk.
+    /// - `Error::Secrets` if the credentials cannot be written to the secret store.
     pub fn save(&self) -> Result<()> {
-        fs::write(
-            &self.config_path,
-            toml::to_string_pretty(self).map_err(TomlError::Serialize)?,
-        )?;
+        if self.overrides.variable(MY_STUDIO_EMAIL_KEY).is_none() {
+            self.secret_store
+                .set(secrets::MY_STUDIO_EMAIL, &self.my_studio.email)?;
+        }
+
+        let contents = if self.overrides.is_empty() {
+            toml::to_string_pretty(self).map_err(TomlError::Serialize)?
+        } else {
+            // the file keeps its own values for the settings set by the environment
+            let mut table = Table::try_from(self).map_err(TomlError::Serialize)?;
+            self.overrides.restore(&mut table);
+            toml::to_string_pretty(&table).map_err(TomlError::Serialize)?
+        };
+        // saving unchanged settings would push the earlier versions out of the backups
+        if fs::read_to_string(&self.config_path).is_ok_and(|current| current == contents) {
+            return Ok(());
+        }
+        if self.config_path.exists() {
+            for n in (1..BACKUP_COUNT).rev() {
+                let backup = backup_path(&self.config_path, n);
+                if backup.exists() {
+                    fs::rename(&backup, backup_path(&self.config_path, n + 1))?;
+                }
+            }
+            fs::copy(&self.config_path, backup_path(&self.config_path, 1))?;
+        }
+        write_atomically(&self.config_path, &contents)?;
 
         Ok(())
     }
+
+    /// Returns whether the credentials are only kept in memory, because there is no secret store to
+    /// save them to. They are lost when the application exits.
+    pub fn has_unsaved_credentials(&self) -> bool {
+        self.secret_store.store().is_none()
+            && !self.my_studio.email.is_empty()
+            && self.overrides.variable(MY_STUDIO_EMAIL_KEY).is_none()
+    }
+
+    /// Returns whether there is a backup that `restore_backup` can restore.
+    pub fn has_backup(&self) -> bool {
+        backup_path(&self.config_path, 1).exists()
+    }
+
+    /// Replaces the configuration with the one saved before it.
+    ///
+    /// The most recent backup becomes the configuration file and the older backups move up, so
+    /// each call goes back one more save. The file it replaces is first copied next to it, e.g. to
+    /// `config.toml.restored.1.bak`, so that the restore can be undone. Credentials are not part
+    /// of the backups and are kept.
+    ///
+    /// # Returns
+    ///
+    /// Returns the path of the copy of the replaced file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NoBackup` if there is no backup, or an error if the replaced file cannot be
+    /// copied or the backup cannot be moved or loaded.
+    pub fn restore_backup(&mut self) -> Result<PathBuf> {
+        let backup = backup_path(&self.config_path, 1);
+        if !backup.exists() {
+            return Err(Error::NoBackup);
+        }
+        // every restore keeps its own copy, since restoring again would replace a shared one
+        let replaced = (1..)
+            .map(|n| {
+                let mut replaced = self.config_path.as_os_str().to_owned();
+                replaced.push(format!(".restored.{n}.bak"));
+                PathBuf::from(replaced)
+            })
+            .find(|replaced| !replaced.exists())
+            .unwrap();
+        fs::copy(&self.config_path, &replaced)?;
+        fs::rename(&backup, &self.config_path)?;
+        for n in 2..=BACKUP_COUNT {
+            let backup = backup_path(&self.config_path, n);
+            if backup.exists() {
+                fs::rename(&backup, backup_path(&self.config_path, n - 1))?;
+            }
+        }
+
+        let mut restored = load(&self.config_path)?;
+        restored.my_studio.email = mem::take(&mut self.my_studio.email);
+        restored.secret_store = self.secret_store.clone();
+        *self = restored;
+
+        Ok(replaced)
+    }
+}
+
+/// Returns the path of the `n`th most recent backup of the configuration file at `path`, e.g.
+/// `config.toml.1.bak`.
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{n}.bak"));
+    PathBuf::from(backup)
+}
+
+/// Replaces the file at `path` with `contents` without ever leaving it partly written.
+///
+/// The contents are written to a temporary file next to it, synced to disk and then renamed over
+/// the file.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file cannot be written or renamed, in which case the file at
+/// `path` is left as it was.
+pub fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temporary, path)?;
+
+    // the rename itself is only durable once the directory is synced
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Where the configuration file was found, and why that path was chosen.
+///
+/// # Fields
+///
+/// * `path` - The path of the configuration file. The file might not exist yet.
+/// * `source` - How the path was chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub source: LocationSource,
+}
+
+/// How the path of the configuration file was chosen, in order of precedence.
+///
+/// # Variants
+///
+/// * `Argument` - The path was given on the command line.
+/// * `Environment` - The path was given in the `PATH_VAR` environment variable.
+/// * `ConfigDir` - The file is in the user's config directory, e.g. `~/.config/youkoso` on Linux.
+/// * `ExecutableDir` - The file is next to the executable, as in portable installs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocationSource {
+    Argument,
+    Environment,
+    ConfigDir,
+    ExecutableDir,
+}
+
+impl fmt::Display for LocationSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationSource::Argument => f.write_str("given with --config"),
+            LocationSource::Environment => write!(f, "given in {PATH_VAR}"),
+            LocationSource::ConfigDir => f.write_str("user config directory"),
+            LocationSource::ExecutableDir => f.write_str("next to the executable"),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.path.display(), self.source)
+    }
+}
+
+/// Chooses the configuration file to use.
+///
+/// An explicit path is used first, then the `PATH_VAR` environment variable. Otherwise the file in
+/// the user's config directory is used if it exists, then the file next to the executable. If
+/// neither exists yet, a new file is created in the user's config directory, or next to the
+/// executable on systems without one.
+///
+/// # Arguments
+///
+/// * `explicit` - The path given on the command line, if any.
+///
+/// # Errors
+///
+/// Returns `Error::NoLocation` if no path was given and neither the user's config directory nor
+/// the executable's directory can be found.
+pub fn locate(explicit: Option<&Path>) -> Result<Location> {
+    let executable_dir = env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+    locate_in(
+        explicit,
+        env::var_os(PATH_VAR),
+        dirs::config_dir(),
+        executable_dir,
+    )
+}
+
+/// Chooses the configuration file to use, as `locate` does, from the given places.
+///
+/// # Arguments
+///
+/// * `explicit` - The path given on the command line, if any.
+/// * `env_path` - The value of the `PATH_VAR` environment variable, if set.
+/// * `config_dir` - The user's config directory, if the system has one.
+/// * `executable_dir` - The directory holding the executable, if it can be found.
+fn locate_in(
+    explicit: Option<&Path>,
+    env_path: Option<OsString>,
+    config_dir: Option<PathBuf>,
+    executable_dir: Option<PathBuf>,
+) -> Result<Location> {
+    if let Some(path) = explicit {
+        return Ok(Location {
+            path: path.to_path_buf(),
+            source: LocationSource::Argument,
+        });
+    }
+    if let Some(path) = env_path.filter(|path| !path.is_empty()) {
+        return Ok(Location {
+            path: PathBuf::from(path),
+            source: LocationSource::Environment,
+        });
+    }
+
+    let config_dir = config_dir.map(|dir| Location {
+        path: dir.join(APP_DIR_NAME).join(FILE_NAME),
+        source: LocationSource::ConfigDir,
+    });
+    let executable_dir = executable_dir.map(|dir| Location {
+        path: dir.join(FILE_NAME),
+        source: LocationSource::ExecutableDir,
+    });
+
+    let candidates = [config_dir, executable_dir];
+    candidates
+        .iter()
+        .flatten()
+        .find(|location| location.path.exists())
+        .or(candidates.iter().flatten().next())
+        .cloned()
+        .ok_or(Error::NoLocation)
 }
 
 /// Loads the configuration from a TOML file at the specified path.
 ///
 /// If the file exists, it reads the contents and attempts to parse it as a TOML configuration.
+/// Files written by older versions are upgraded first, see `migration::upgrade`. Settings that are
+/// missing use their defaults, and so do settings that are invalid, which are reported in
+/// `Config::warnings`. When a value in the file was replaced by its default, the file is copied
+/// next to itself with an `.invalid.bak` extension, since that value is lost the next time it is
+/// saved.
+/// Settings given in environment variables are applied on top of the file, see `Overrides`.
 /// If the file does not exist, it creates a default configuration, serializes it to TOML,
-/// and writes it to the specified path.
+/// and writes it to the specified path, creating its parent directories as needed.
 ///
 /// # Arguments
 ///
@@ -155,22 +889,285 @@ impl Config {
 ///
 /// This function can return an error in the following cases:
 /// - If the file exists but cannot be read.
-/// - If the file contents cannot be parsed as valid TOML.
+/// - If the file is from an older version and cannot be upgraded or backed up.
 /// - If the default configuration cannot be serialized to TOML.
-/// - If the default configuration cannot be written to the specified path.
+/// - If the default configuration or its directory cannot be written to the specified path.
 pub fn load(config_path: &Path) -> Result<Config> {
-    let mut config: Config;
-
-    if config_path.exists() {
-        let contents = fs::read_to_string(config_path)?;
-        config = toml::from_str(&contents).map_err(TomlError::Deserialize)?;
-    } else {
-        config = Config::default();
-        let default_config = toml::to_string(&config).map_err(TomlError::Serialize)?;
-        fs::write(config_path, default_config)?;
+    if !config_path.exists() {
+        let default_config = toml::to_string(&Config::default()).map_err(TomlError::Serialize)?;
+        if let Some(dir) = config_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_atomically(config_path, &default_config)?;
     }
 
+    let contents = fs::read_to_string(config_path)?;
+    let mut warnings = Vec::new();
+    let mut table = match toml::from_str::<Table>(&contents) {
+        Ok(mut table) => {
+            migration::upgrade(config_path, &mut table, &mut warnings)?;
+            table
+        }
+        Err(e) => {
+            warnings.push(Warning::Unreadable {
+                message: e.message().trim().to_owned(),
+            });
+            Table::new()
+        }
+    };
+    let overrides = Overrides::apply(&mut table, &mut warnings);
+    let mut config = lenient::deserialize(table, &mut warnings);
+
+    // invalid overrides are never saved, so only the file's own values are worth backing up
+    let replaces_file_values = warnings.iter().any(|warning| match warning {
+        Warning::Unreadable { .. } => true,
+        Warning::InvalidValue { key, .. } => overrides.variable(key).is_none(),
+        _ => false,
+    });
+    if replaces_file_values {
+        let mut backup = config_path.as_os_str().to_owned();
+        backup.push(".invalid.bak");
+        fs::write(backup, contents)?;
+    }
+    config.warnings = warnings;
+    config.overrides = overrides;
     config.config_path = config_path.to_path_buf();
 
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn email_is_not_saved_to_the_file_without_a_secret_store() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+
+        let mut config = load(&path).unwrap();
+        config.my_studio.email = "frontdesk@example.com".to_owned();
+        config.save().unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+
+        assert!(!contents.contains("frontdesk@example.com"));
+        assert!(config.has_unsaved_credentials());
+    }
+
+    #[test]
+    fn default_id_normalization_only_trims() {
+        let normalization = IdNormalization::default();
+
+        assert_eq!(normalization.normalize("  00Ab12 "), "00Ab12");
+    }
+
+    #[test]
+    fn id_normalization_rules_combine() {
+        let normalization = IdNormalization {
+            trim: true,
+            case: IdCase::Upper,
+            strip_leading_zeros: true,
+            integer_floats: true,
+        };
+
+        assert_eq!(normalization.normalize(" 00ab12\t"), "AB12");
+        assert_eq!(normalization.normalize("000"), "0");
+        assert_eq!(normalization.normalize("ab0"), "AB0");
+    }
+
+    #[test]
+    fn leading_zeros_are_only_stripped_after_trimming() {
+        let normalization = IdNormalization {
+            trim: false,
+            case: IdCase::Lower,
+            strip_leading_zeros: true,
+            integer_floats: true,
+        };
+
+        assert_eq!(normalization.normalize(" 0012"), " 0012");
+        assert_eq!(normalization.normalize("00AB"), "ab");
+    }
+
+    #[test]
+    fn any_listed_symbol_enables_immediate_sign_in() {
+        let immediate_sign_in = ImmediateSignIn {
+            enabled_symbol: "TRUE, Y,,x ".to_owned(),
+            ..Default::default()
+        };
+
+        assert!(immediate_sign_in.is_enabled("TRUE"));
+        assert!(immediate_sign_in.is_enabled("Y"));
+        assert!(immediate_sign_in.is_enabled("x"));
+        assert!(!immediate_sign_in.is_enabled(""));
+        assert!(!immediate_sign_in.is_enabled("TRUE, Y"));
+        assert!(!immediate_sign_in.is_enabled("N"));
+    }
+
+    #[test]
+    fn immediate_sign_in_ignores_case_by_default() {
+        let immediate_sign_in = ImmediateSignIn::default();
+
+        assert!(immediate_sign_in.is_enabled("TRUE"));
+        assert!(immediate_sign_in.is_enabled("true"));
+        assert!(immediate_sign_in.is_enabled("True"));
+        assert!(!immediate_sign_in.is_enabled("FALSE"));
+    }
+
+    #[test]
+    fn case_sensitive_immediate_sign_in_matches_case_exactly() {
+        let immediate_sign_in = ImmediateSignIn {
+            enabled_symbol: "Y".to_owned(),
+            case_sensitive: true,
+            ..Default::default()
+        };
+
+        assert!(immediate_sign_in.is_enabled("Y"));
+        assert!(!immediate_sign_in.is_enabled("y"));
+    }
+
+    #[test]
+    fn immediate_sign_in_ignores_surrounding_whitespace() {
+        let immediate_sign_in = ImmediateSignIn {
+            enabled_symbol: " Y ".to_owned(),
+            ..Default::default()
+        };
+
+        assert!(immediate_sign_in.is_enabled("Y"));
+        assert!(immediate_sign_in.is_enabled("\ty \n"));
+        assert!(!immediate_sign_in.is_enabled("Y Y"));
+    }
+
+    #[test]
+    fn config_is_located_in_order() {
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path().join("config");
+        let executable_dir = dir.path().join("bin");
+        let locate = |explicit: Option<&Path>, env_path: &str| {
+            locate_in(
+                explicit,
+                Some(env_path.into()),
+                Some(config_dir.clone()),
+                Some(executable_dir.clone()),
+            )
+            .unwrap()
+        };
+
+        let location = locate(Some(Path::new("given.toml")), "env.toml");
+        assert_eq!(location.path, Path::new("given.toml"));
+        assert_eq!(location.source, LocationSource::Argument);
+
+        let location = locate(None, "env.toml");
+        assert_eq!(location.path, Path::new("env.toml"));
+        assert_eq!(location.source, LocationSource::Environment);
+
+        // neither file exists yet, so a new one goes in the config directory
+        let location = locate(None, "");
+        assert_eq!(location.path, config_dir.join(APP_DIR_NAME).join(FILE_NAME));
+        assert_eq!(location.source, LocationSource::ConfigDir);
+
+        fs::create_dir_all(&executable_dir).unwrap();
+        fs::write(executable_dir.join(FILE_NAME), "").unwrap();
+        let location = locate(None, "");
+        assert_eq!(location.path, executable_dir.join(FILE_NAME));
+        assert_eq!(location.source, LocationSource::ExecutableDir);
+
+        fs::create_dir_all(config_dir.join(APP_DIR_NAME)).unwrap();
+        fs::write(config_dir.join(APP_DIR_NAME).join(FILE_NAME), "").unwrap();
+        let location = locate(None, "");
+        assert_eq!(location.source, LocationSource::ConfigDir);
+    }
+
+    #[test]
+    fn config_is_located_next_to_the_executable_without_a_config_dir() {
+        let dir = tempdir().unwrap();
+
+        let location = locate_in(None, None, None, Some(dir.path().to_path_buf())).unwrap();
+        assert_eq!(location.path, dir.path().join(FILE_NAME));
+        assert_eq!(location.source, LocationSource::ExecutableDir);
+
+        assert!(matches!(
+            locate_in(None, None, None, None),
+            Err(Error::NoLocation)
+        ));
+    }
+
+    #[test]
+    fn days_are_parsed_as_zero_padded_month_and_day() {
+        assert_eq!(parse_day("4-1").as_deref(), Some("04-01"));
+        assert_eq!(parse_day(" 12 - 31 ").as_deref(), Some("12-31"));
+        assert_eq!(parse_day("02-29").as_deref(), Some("02-29"));
+    }
+
+    #[test]
+    fn invalid_days_are_rejected() {
+        for day in [
+            "02-30",
+            "13-01",
+            "00-10",
+            "04-00",
+            "0401",
+            "April 1",
+            "04-01-2026",
+            "",
+        ] {
+            assert_eq!(parse_day(day), None, "{day}");
+        }
+    }
+
+    #[test]
+    fn greeting_prefers_a_message_for_today() {
+        let message = |student_id: &str, text: &str, day: Option<&str>| GreetingMessage {
+            student_id: student_id.to_owned(),
+            text: text.to_owned(),
+            day: day.map(str::to_owned),
+        };
+        let greeting = Greeting {
+            messages: vec![
+                message("00123", "Welcome back!", None),
+                message("00123", "Happy birthday!", Some("04-01")),
+                message("00124", "Good luck today!", Some("04-02")),
+            ],
+            ..Greeting::default()
+        };
+        let date = |day| Date::from_calendar_date(2026, Month::April, day).unwrap();
+
+        assert_eq!(
+            greeting.message_for("00123", date(1)),
+            Some("Happy birthday!")
+        );
+        assert_eq!(
+            greeting.message_for("00123", date(2)),
+            Some("Welcome back!")
+        );
+        assert_eq!(
+            greeting.message_for("00124", date(2)),
+            Some("Good luck today!")
+        );
+        assert_eq!(greeting.message_for("00124", date(1)), None);
+        assert_eq!(greeting.message_for("00125", date(1)), None);
+    }
+
+    #[test]
+    fn restoring_a_backup_keeps_the_replaced_settings() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+
+        let mut config = load(&path).unwrap();
+        for theme in [Theme::Dark, Theme::Light] {
+            config.theme = theme;
+            config.save().unwrap();
+        }
+        let first = config.restore_backup().unwrap();
+        let theme_after_first = config.theme.clone();
+        let second = config.restore_backup().unwrap();
+        let first_contents = fs::read_to_string(&first).unwrap();
+        let second_contents = fs::read_to_string(&second).unwrap();
+
+        assert_eq!(theme_after_first, Theme::Dark);
+        assert_ne!(first, second);
+        assert!(first_contents.contains("Light"));
+        assert!(second_contents.contains("Dark"));
+    }
+}
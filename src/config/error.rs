@@ -12,6 +12,11 @@ pub type Result<T> = std::result::Result<T, Error>;
 ///
 /// * `Io` - Represents I/O errors that occur during file operations.
 /// * `Toml` - Represents errors related to TOML serialization or deserialization.
+/// * `PinTooShort` - Indicates that a new admin PIN is shorter than allowed.
+/// * `PinHash` - Represents errors when hashing an admin PIN.
+/// * `Secrets` - Represents errors when reading or writing credentials in the secret store.
+/// * `NoLocation` - Indicates that there is nowhere to look for the configuration file.
+/// * `NoBackup` - Indicates that there is no earlier version of the configuration to restore.
 ///
 /// # Example
 ///
@@ -39,6 +44,72 @@ pub enum Error {
     /// cannot be serialized to or deserialized from TOML format.
     #[error(transparent)]
     Toml(#[from] TomlError),
+
+    /// Indicates that a new admin PIN does not have enough characters to be hard to guess.
+    #[error("The PIN must have at least {min_length} characters.")]
+    PinTooShort { min_length: usize },
+
+    /// Represents errors when hashing an admin PIN, originating from the `argon2` library.
+    #[error("Cannot hash the PIN: {0}")]
+    PinHash(argon2::password_hash::Error),
+
+    /// Represents errors when reading or writing credentials, originating from the `secrets`
+    /// module.
+    #[error(transparent)]
+    Secrets(#[from] crate::secrets::Error),
+
+    /// Indicates that no path was given and neither the user's config directory nor the
+    /// executable's directory could be found.
+    #[error("Cannot find a place for the configuration file, use --config to give its path.")]
+    NoLocation,
+
+    /// Indicates that the configuration has no backup to restore, e.g. because it was never
+    /// changed.
+    #[error("There are no earlier settings to restore.")]
+    NoBackup,
+}
+
+/// Represents problems found in the configuration file that did not stop it from loading.
+///
+/// Settings with problems fall back to their defaults, so that a typo does not keep the kiosk from
+/// starting. They are replaced in the file the next time the settings are saved.
+///
+/// # Variants
+///
+/// * `Unreadable` - Indicates that the file is not valid TOML, so every setting uses its default.
+/// * `InvalidValue` - Indicates that a setting has a value of the wrong type or an unknown option.
+/// * `UnknownKey` - Indicates that a setting is not known to this version and is ignored.
+/// * `NewerVersion` - Indicates that the file was written by a newer version of the application.
+/// * `InvalidOverride` - Indicates that an environment variable does not name a setting.
+/// * `NonUnicodeOverride` - Indicates that an environment variable is not valid Unicode.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Warning {
+    /// Indicates that the file cannot be parsed as TOML at all.
+    #[error("The file is not valid TOML, every setting uses its default: {message}")]
+    Unreadable { message: String },
+
+    /// Indicates that a setting cannot be read, so its default is used instead.
+    #[error("'{key}' is invalid, the default is used instead: {message}")]
+    InvalidValue { key: String, message: String },
+
+    /// Indicates that a setting is not used by this version, e.g. because of a typo in its name.
+    #[error("'{key}' is not a known setting and is ignored.")]
+    UnknownKey { key: String },
+
+    /// Indicates that the file has a version newer than `CURRENT_VERSION`, so some of its settings
+    /// might not be understood.
+    #[error("The file was written by a newer version of youkoso (version {version}).")]
+    NewerVersion { version: u32 },
+
+    /// Indicates that an environment variable overrides a setting inside a value instead of a
+    /// section, e.g. `YOUKOSO_THEME__DARK`.
+    #[error("'{variable}' does not name a setting and is ignored.")]
+    InvalidOverride { variable: String },
+
+    /// Indicates that the name or value of an environment variable that looks like an override is
+    /// not valid Unicode. The name is shown with the invalid characters replaced.
+    #[error("'{variable}' is not valid Unicode and is ignored.")]
+    NonUnicodeOverride { variable: String },
 }
 
 /// Represents specific errors related to TOML operations.
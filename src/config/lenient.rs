@@ -0,0 +1,249 @@
+use serde_path_to_error::Segment;
+use toml::{Table, Value};
+
+use super::Config;
+use super::error::Warning;
+
+/// Deserializes a configuration, falling back to the default of every invalid setting.
+///
+/// Each time deserialization fails, the setting it failed at is replaced with its default, or
+/// removed if it has none, such as an entry of a list, and deserialization is tried again. If a
+/// setting cannot be repaired, the section around it is replaced instead. Missing settings use
+/// their defaults without a warning.
+///
+/// # Arguments
+///
+/// * `table` - The raw configuration, as read from the file.
+/// * `warnings` - Collects a warning for every setting that was replaced or ignored.
+pub fn deserialize(mut table: Table, warnings: &mut Vec<Warning>) -> Config {
+    let defaults = Table::try_from(Config::default()).unwrap_or_default();
+
+    loop {
+        let mut unknown = Vec::new();
+        let mut ignored = |key: serde_ignored::Path| {
+            unknown.push(Warning::UnknownKey {
+                key: key.to_string(),
+            });
+        };
+        let deserializer = serde_ignored::Deserializer::new(table.clone(), &mut ignored);
+        let e = match serde_path_to_error::deserialize(deserializer) {
+            Ok(config) => {
+                warnings.extend(unknown);
+                return config;
+            }
+            Err(e) => e,
+        };
+
+        let segments: Vec<&Segment> = e.path().iter().collect();
+        warnings.push(Warning::InvalidValue {
+            key: e.path().to_string(),
+            message: e.inner().message().trim().to_owned(),
+        });
+        // repairing a setting always changes the table, so this cannot repeat forever
+        let repaired = (1..=segments.len())
+            .rev()
+            .any(|length| repair(&mut table, &defaults, &segments[..length]));
+        if !repaired {
+            warnings.extend(unknown);
+            return Config::default();
+        }
+    }
+}
+
+/// Replaces the value at `path` with its default, or removes it if it has no default.
+///
+/// # Returns
+///
+/// Returns whether the table was changed.
+fn repair(table: &mut Table, defaults: &Table, path: &[&Segment]) -> bool {
+    let Some((last, parents)) = path.split_last() else {
+        return false;
+    };
+    let default = lookup(defaults, path).cloned();
+
+    let mut parent = Parent::Table(table);
+    for segment in parents {
+        let child = match (parent, segment) {
+            (Parent::Table(table), Segment::Map { key }) => table.get_mut(key),
+            (Parent::Array(array), Segment::Seq { index }) => array.get_mut(*index),
+            _ => return false,
+        };
+        parent = match child {
+            Some(Value::Table(table)) => Parent::Table(table),
+            Some(Value::Array(array)) => Parent::Array(array),
+            _ => return false,
+        };
+    }
+
+    match (parent, last, default) {
+        (Parent::Table(table), Segment::Map { key }, Some(default)) => {
+            table.insert(key.clone(), default.clone()) != Some(default)
+        }
+        (Parent::Table(table), Segment::Map { key }, None) => table.remove(key).is_some(),
+        (Parent::Array(array), Segment::Seq { index }, _) if *index < array.len() => {
+            array.remove(*index);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Finds the value at `path` among the defaults. Entries of lists have no default.
+fn lookup<'a>(defaults: &'a Table, path: &[&Segment]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    let Segment::Map { key } = first else {
+        return None;
+    };
+
+    rest.iter()
+        .try_fold(defaults.get(key)?, |value, segment| match segment {
+            Segment::Map { key } => value.get(key),
+            _ => None,
+        })
+}
+
+/// A table or array that a setting is found in.
+enum Parent<'a> {
+    Table(&'a mut Table),
+    Array(&'a mut Vec<Value>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Theme;
+
+    fn deserialize_str(contents: &str) -> (Config, Vec<Warning>) {
+        let mut warnings = Vec::new();
+        let config = deserialize(toml::from_str(contents).unwrap(), &mut warnings);
+        (config, warnings)
+    }
+
+    fn invalid_keys(warnings: &[Warning]) -> Vec<&str> {
+        warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                Warning::InvalidValue { key, .. } => Some(key.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn invalid_nested_value_falls_back_to_its_default() {
+        let (config, warnings) = deserialize_str(
+            r#"
+            theme = "Dark"
+
+            [kiosk]
+            name_search = true
+
+            [kiosk.greeting]
+            duration_secs = "long"
+            "#,
+        );
+
+        assert_eq!(config.kiosk.greeting.duration_secs, 3);
+        assert_eq!(config.theme, Theme::Dark);
+        assert!(config.kiosk.name_search);
+        assert_eq!(invalid_keys(&warnings), ["kiosk.greeting.duration_secs"]);
+    }
+
+    #[test]
+    fn every_invalid_value_is_reported() {
+        let (config, warnings) = deserialize_str(
+            r#"
+            theme = "Purple"
+
+            [my_studio]
+            company_id = "12345"
+
+            [student_data]
+            sheet_name = "Roster"
+            delimiter = 5
+
+            [kiosk.scanner]
+            enabled = "yes"
+            "#,
+        );
+
+        assert_eq!(config.theme, Theme::System);
+        assert_eq!(config.my_studio.company_id, "12345");
+        assert_eq!(config.student_data.sheet_name, "Roster");
+        assert_eq!(config.student_data.delimiter, None);
+        assert!(!config.kiosk.scanner.enabled);
+        assert_eq!(
+            invalid_keys(&warnings),
+            ["kiosk.scanner.enabled", "student_data.delimiter", "theme"]
+        );
+    }
+
+    #[test]
+    fn invalid_list_entry_is_removed() {
+        let (config, warnings) = deserialize_str(
+            r#"
+            [[kiosk.greeting.messages]]
+            student_id = "1"
+            text = "Happy birthday!"
+            day = "13-45"
+
+            [[kiosk.greeting.messages]]
+            student_id = "2"
+            text = "Welcome back!"
+            "#,
+        );
+
+        assert_eq!(config.kiosk.greeting.messages.len(), 1);
+        assert_eq!(config.kiosk.greeting.messages[0].student_id, "2");
+        assert_eq!(invalid_keys(&warnings), ["kiosk.greeting.messages[0]"]);
+    }
+
+    #[test]
+    fn malformed_sections_are_replaced_without_looping() {
+        let (config, warnings) = deserialize_str(
+            r#"
+            theme = "Light"
+            kiosk = 5
+            student_data = true
+
+            [my_studio.company_id]
+            nested = true
+            "#,
+        );
+
+        assert_eq!(config.theme, Theme::Light);
+        assert_eq!(config.kiosk, Config::default().kiosk);
+        assert_eq!(config.student_data, Config::default().student_data);
+        assert_eq!(config.my_studio, Config::default().my_studio);
+        assert_eq!(
+            invalid_keys(&warnings),
+            ["kiosk", "my_studio.company_id", "student_data"]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_once() {
+        let (config, warnings) = deserialize_str(
+            r#"
+            theme = "Dark"
+            colour = "blue"
+
+            [kiosk]
+            name_serch = true
+            "#,
+        );
+
+        assert_eq!(config.theme, Theme::Dark);
+        assert_eq!(
+            warnings,
+            [
+                Warning::UnknownKey {
+                    key: "colour".to_owned()
+                },
+                Warning::UnknownKey {
+                    key: "kiosk.name_serch".to_owned()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::{fs, path::Path};
+
+use toml::{Table, Value};
+
+use super::error::{Result, TomlError, Warning};
+use super::write_atomically;
+
+/// The version of the configuration format written by this build.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Upgrades a configuration from one version to the next.
+///
+/// The migration at index `n` upgrades version `n` to version `n + 1`. It edits the raw table, so
+/// keys it does not know about are left as they are.
+type Migration = fn(&mut Table);
+
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [v0_to_v1];
+
+/// Reads the version of a raw configuration. Files written before the version key was added are
+/// version 0.
+///
+/// # Returns
+///
+/// Returns `None` if the version is not a non-negative integer.
+fn version(table: &Table) -> Option<u32> {
+    match table.get("version") {
+        None => Some(0),
+        Some(Value::Integer(version)) => u32::try_from(*version).ok(),
+        Some(_) => None,
+    }
+}
+
+/// Upgrades the configuration file at `path` to `CURRENT_VERSION`, if it is older.
+///
+/// The file is upgraded in place. Before it is replaced, the original is copied next to it with
+/// the old version in its name, e.g. `config.toml.v0.bak`. Files with an invalid version are left
+/// as they are, the invalid version is reported when the configuration is deserialized.
+///
+/// # Arguments
+///
+/// * `path` - The path of the configuration file.
+/// * `table` - The raw configuration read from the file, which is upgraded.
+/// * `warnings` - Collects a warning if the file was written by a newer version.
+///
+/// # Errors
+///
+/// Returns an error if the backup or the upgraded file cannot be written.
+pub fn upgrade(path: &Path, table: &mut Table, warnings: &mut Vec<Warning>) -> Result<()> {
+    let Some(from) = version(table) else {
+        return Ok(());
+    };
+    if from > CURRENT_VERSION {
+        warnings.push(Warning::NewerVersion { version: from });
+        return Ok(());
+    }
+    if from == CURRENT_VERSION {
+        return Ok(());
+    }
+
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(table);
+    }
+    table.insert("version".to_owned(), Value::Integer(CURRENT_VERSION.into()));
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{from}.bak"));
+    fs::copy(path, &backup)?;
+    write_atomically(
+        path,
+        &toml::to_string_pretty(table).map_err(TomlError::Serialize)?,
+    )?;
+    eprintln!(
+        "Upgraded '{}' from version {from} to version {CURRENT_VERSION}, the old file was kept \
+         as '{}'",
+        path.display(),
+        Path::new(&backup).display()
+    );
+
+    Ok(())
+}
+
+/// Removes the MyStudio password, which older READMEs asked for but was never used. It is a
+/// credential, so it is dropped instead of being left in the file.
+fn v0_to_v1(table: &mut Table) {
+    if let Some(Value::Table(my_studio)) = table.get_mut("my_studio")
+        && my_studio.remove("password").is_some()
+    {
+        eprintln!("Removed the unused MyStudio password from the configuration");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tempfile::{TempDir, tempdir};
+
+    use super::*;
+
+    /// The example configuration from the README before the version key was added.
+    const README_V0: &str = r#"theme = "System" # Options: "System", "Dark", "Light"
+
+[my_studio]
+email = "user@example.com"
+password = "your_password"
+company_id = "12345"
+"#;
+
+    fn write_config(contents: &str) -> (TempDir, PathBuf) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn readme_file_is_upgraded_to_v1_without_the_password() {
+        let (dir, path) = write_config(README_V0);
+        let mut table: Table = toml::from_str(README_V0).unwrap();
+        let mut warnings = Vec::new();
+
+        upgrade(&path, &mut table, &mut warnings).unwrap();
+        let upgraded = fs::read_to_string(&path).unwrap();
+        let backup = fs::read_to_string(dir.path().join("config.toml.v0.bak")).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(table["version"], Value::Integer(1));
+        assert_eq!(table["my_studio"].get("password"), None);
+        assert_eq!(
+            table["my_studio"]["company_id"],
+            Value::String("12345".to_owned())
+        );
+        assert_eq!(toml::from_str::<Table>(&upgraded).unwrap(), table);
+        assert_eq!(backup, README_V0);
+    }
+
+    #[test]
+    fn current_and_newer_files_are_left_as_they_are() {
+        let contents = "version = 2\n";
+        let (dir, path) = write_config(contents);
+        let mut table: Table = toml::from_str(contents).unwrap();
+        let mut warnings = Vec::new();
+
+        upgrade(&path, &mut table, &mut warnings).unwrap();
+        let unchanged = fs::read_to_string(&path).unwrap();
+        let backed_up = dir.path().join("config.toml.v2.bak").exists();
+
+        assert_eq!(warnings, [Warning::NewerVersion { version: 2 }]);
+        assert_eq!(unchanged, contents);
+        assert!(!backed_up);
+    }
+
+    #[test]
+    fn unknown_keys_are_still_reported_after_upgrading() {
+        let contents = format!("{README_V0}companyid = \"12345\"\n");
+        let (_dir, path) = write_config(&contents);
+
+        let config = super::super::load(&path).unwrap();
+
+        assert_eq!(
+            config.warnings(),
+            [Warning::UnknownKey {
+                key: "my_studio.companyid".to_owned()
+            }]
+        );
+    }
+}
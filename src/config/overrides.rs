@@ -0,0 +1,353 @@
+use std::{collections::BTreeMap, env, ffi::OsString};
+
+use toml::{Table, Value};
+
+use super::error::Warning;
+use super::{Config, PATH_VAR};
+use crate::secrets::PASSPHRASE_VAR;
+
+/// The prefix of the environment variables that override settings.
+pub const PREFIX: &str = "YOUKOSO_";
+/// Separates the sections of a setting in the name of an environment variable.
+const SEPARATOR: &str = "__";
+
+/// The settings replaced by environment variables.
+///
+/// A setting is overridden by the variable named after its key, in upper case, with sections
+/// separated by `SEPARATOR`, e.g. `YOUKOSO_MY_STUDIO__EMAIL` for `my_studio.email`. Overridden
+/// settings are never written to the configuration file, which keeps its own values.
+///
+/// # Fields
+///
+/// * `overrides` - The overridden settings by key, e.g. `"my_studio.email"`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    overrides: BTreeMap<String, Override>,
+}
+
+/// A setting replaced by an environment variable.
+///
+/// # Fields
+///
+/// * `variable` - The name of the environment variable.
+/// * `original` - The value in the configuration file, or `None` if the file does not set it.
+#[derive(Debug, Clone, PartialEq)]
+struct Override {
+    variable: String,
+    original: Option<Value>,
+}
+
+impl Overrides {
+    /// Applies the overrides in the environment to a raw configuration.
+    ///
+    /// Values are read as TOML, e.g. `true`, `5` or `["a", "b"]`, unless the setting is text, has
+    /// no default, or the value is not valid TOML, in which case the value is used as it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The raw configuration read from the file, which is changed.
+    /// * `warnings` - Collects a warning for every variable that cannot be applied.
+    pub fn apply(table: &mut Table, warnings: &mut Vec<Warning>) -> Self {
+        // unlike `env::vars`, this does not panic on variables that are not valid Unicode, which
+        // any unrelated variable might be
+        Self::apply_vars(env::vars_os(), table, warnings)
+    }
+
+    /// Applies the overrides in `vars` to a raw configuration, like `apply` does with the
+    /// environment.
+    fn apply_vars(
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+        table: &mut Table,
+        warnings: &mut Vec<Warning>,
+    ) -> Self {
+        let defaults = Table::try_from(Config::default()).unwrap_or_default();
+        let mut overrides = BTreeMap::new();
+
+        for (variable, value) in vars {
+            let lossy = variable.to_string_lossy();
+            if !lossy.starts_with(PREFIX)
+                || lossy == PREFIX
+                || lossy == PATH_VAR
+                || lossy == PASSPHRASE_VAR
+            {
+                continue;
+            }
+            let (Some(variable), Ok(value)) = (variable.to_str(), value.into_string()) else {
+                warnings.push(Warning::NonUnicodeOverride {
+                    variable: lossy.into_owned(),
+                });
+                continue;
+            };
+            let variable = variable.to_owned();
+            let name = &variable[PREFIX.len()..];
+            let path: Vec<String> = name.split(SEPARATOR).map(str::to_lowercase).collect();
+
+            // a section missing from the file must not be created where a value belongs
+            let in_value = (1..path.len())
+                .any(|length| lookup(&defaults, &path[..length]).is_some_and(|v| !v.is_table()));
+            let section = if in_value {
+                None
+            } else {
+                section(table, &path)
+            };
+            let Some(section) = section else {
+                warnings.push(Warning::InvalidOverride { variable });
+                continue;
+            };
+            let key = path.last().unwrap();
+            // settings without a default in the table are either skipped when serialized, like
+            // the email, or optional text, like the encoding, so they are text as well
+            let is_text = matches!(section.get(key), Some(Value::String(_)))
+                || matches!(lookup(&defaults, &path), Some(Value::String(_)) | None);
+            let value = if is_text {
+                Value::String(value)
+            } else {
+                parse(&value).unwrap_or(Value::String(value))
+            };
+
+            let original = section.insert(key.clone(), value);
+            overrides.insert(path.join("."), Override { variable, original });
+        }
+
+        Self { overrides }
+    }
+
+    /// Returns the environment variable that overrides the setting with the given key, or one of
+    /// its sections.
+    pub fn variable(&self, key: &str) -> Option<&str> {
+        self.overrides
+            .iter()
+            .find(|(overridden, _)| {
+                key == overridden.as_str()
+                    || key
+                        .strip_prefix(overridden.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .map(|(_, value)| value.variable.as_str())
+    }
+
+    /// Puts the values of the configuration file back in place of the overridden settings of a
+    /// configuration that is about to be saved.
+    pub fn restore(&self, table: &mut Table) {
+        for (key, value) in &self.overrides {
+            let path: Vec<String> = key.split('.').map(str::to_owned).collect();
+            let Some(section) = section(table, &path) else {
+                continue;
+            };
+            let key = path.last().unwrap();
+            match &value.original {
+                Some(original) => section.insert(key.clone(), original.clone()),
+                None => section.remove(key),
+            };
+        }
+    }
+
+    /// Returns whether no settings are overridden.
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+}
+
+/// Returns the section holding the setting at `path`, creating missing sections.
+///
+/// # Returns
+///
+/// Returns `None` if part of the path is a value instead of a section.
+fn section<'a>(table: &'a mut Table, path: &[String]) -> Option<&'a mut Table> {
+    let (_, sections) = path.split_last()?;
+    sections.iter().try_fold(table, |table, key| {
+        match table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()))
+        {
+            Value::Table(section) => Some(section),
+            _ => None,
+        }
+    })
+}
+
+/// Finds the value at `path` among the defaults.
+fn lookup<'a>(defaults: &'a Table, path: &[String]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(defaults.get(first)?, |value, key| value.get(key))
+}
+
+/// Reads a value written as TOML, e.g. `true` or `[1, 2]`.
+fn parse(value: &str) -> Option<Value> {
+    toml::from_str::<Table>(&format!("value = {value}"))
+        .ok()?
+        .remove("value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(vars: &[(&str, &str)], contents: &str) -> (Table, Overrides, Vec<Warning>) {
+        let mut table: Table = toml::from_str(contents).unwrap();
+        let mut warnings = Vec::new();
+        let overrides = Overrides::apply_vars(
+            vars.iter()
+                .map(|(variable, value)| (variable.into(), value.into())),
+            &mut table,
+            &mut warnings,
+        );
+        (table, overrides, warnings)
+    }
+
+    #[test]
+    fn variable_names_map_to_nested_settings() {
+        let (table, overrides, warnings) = apply(
+            &[
+                ("YOUKOSO_KIOSK__SCANNER__ENABLED", "true"),
+                ("YOUKOSO_THEME", "Dark"),
+            ],
+            "",
+        );
+
+        assert_eq!(table["kiosk"]["scanner"]["enabled"], Value::Boolean(true));
+        assert_eq!(table["theme"], Value::String("Dark".to_owned()));
+        assert_eq!(
+            overrides.variable("kiosk.scanner.enabled"),
+            Some("YOUKOSO_KIOSK__SCANNER__ENABLED")
+        );
+        assert_eq!(overrides.variable("kiosk.scanner.prefix"), None);
+        assert_eq!(overrides.variable("theme"), Some("YOUKOSO_THEME"));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn overriding_a_section_overrides_its_settings() {
+        let (table, overrides, _) = apply(
+            &[(
+                "YOUKOSO_KIOSK__SCANNER",
+                r#"{ enabled = true, prefix = "ID:" }"#,
+            )],
+            "",
+        );
+
+        assert_eq!(
+            table["kiosk"]["scanner"]["prefix"],
+            Value::String("ID:".to_owned())
+        );
+        assert_eq!(
+            overrides.variable("kiosk.scanner.prefix"),
+            Some("YOUKOSO_KIOSK__SCANNER")
+        );
+        assert_eq!(overrides.variable("kiosk.scanner_extra"), None);
+    }
+
+    #[test]
+    fn values_are_typed_like_their_defaults() {
+        let (table, _, _) = apply(
+            &[
+                ("YOUKOSO_KIOSK__GREETING__DURATION_SECS", "10"),
+                ("YOUKOSO_MY_STUDIO__COMPANY_ID", "12345"),
+                ("YOUKOSO_STUDENT_DATA__SHEET_NAME", "true"),
+            ],
+            "",
+        );
+
+        assert_eq!(
+            table["kiosk"]["greeting"]["duration_secs"],
+            Value::Integer(10)
+        );
+        assert_eq!(
+            table["my_studio"]["company_id"],
+            Value::String("12345".to_owned())
+        );
+        assert_eq!(
+            table["student_data"]["sheet_name"],
+            Value::String("true".to_owned())
+        );
+    }
+
+    #[test]
+    fn settings_without_a_default_are_read_as_text() {
+        let (table, _, _) = apply(
+            &[
+                ("YOUKOSO_STUDENT_DATA__ENCODING", "1252"),
+                ("YOUKOSO_STUDENT_DATA__DELIMITER", ";"),
+                ("YOUKOSO_MY_STUDIO__EMAIL", "frontdesk@example.com"),
+            ],
+            "",
+        );
+
+        assert_eq!(
+            table["student_data"]["encoding"],
+            Value::String("1252".to_owned())
+        );
+        assert_eq!(
+            table["student_data"]["delimiter"],
+            Value::String(";".to_owned())
+        );
+        assert_eq!(
+            table["my_studio"]["email"],
+            Value::String("frontdesk@example.com".to_owned())
+        );
+    }
+
+    #[test]
+    fn variables_inside_a_value_and_reserved_variables_are_not_applied() {
+        let (table, overrides, warnings) = apply(
+            &[
+                ("YOUKOSO_THEME__DARK", "true"),
+                (PATH_VAR, "/tmp/config.toml"),
+                (PASSPHRASE_VAR, "secret"),
+                ("OTHER_VARIABLE", "1"),
+            ],
+            r#"theme = "Light""#,
+        );
+
+        assert_eq!(table["theme"], Value::String("Light".to_owned()));
+        assert!(overrides.is_empty());
+        assert_eq!(
+            warnings,
+            [Warning::InvalidOverride {
+                variable: "YOUKOSO_THEME__DARK".to_owned()
+            }]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_variables_are_reported() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let mut table = Table::new();
+        let mut warnings = Vec::new();
+        let overrides = Overrides::apply_vars(
+            [(
+                OsString::from("YOUKOSO_THEME"),
+                OsString::from_vec(vec![0x44, 0xff]),
+            )],
+            &mut table,
+            &mut warnings,
+        );
+
+        assert!(overrides.is_empty());
+        assert_eq!(
+            warnings,
+            [Warning::NonUnicodeOverride {
+                variable: "YOUKOSO_THEME".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn restore_puts_back_the_values_of_the_file() {
+        let (mut table, overrides, _) = apply(
+            &[
+                ("YOUKOSO_THEME", "Dark"),
+                ("YOUKOSO_KIOSK__NAME_SEARCH", "true"),
+            ],
+            r#"theme = "Light""#,
+        );
+
+        overrides.restore(&mut table);
+
+        assert_eq!(table["theme"], Value::String("Light".to_owned()));
+        assert_eq!(table["kiosk"].get("name_search"), None);
+    }
+}
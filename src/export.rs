@@ -0,0 +1,488 @@
+mod error;
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fs,
+    path::Path,
+};
+
+pub use error::Error;
+use error::Result;
+use rust_xlsxwriter::{ExcelDateTime, Format as CellFormat, Workbook};
+use time::{Date, OffsetDateTime, format_description::well_known::Iso8601};
+
+use crate::attendance::{self, AttendanceLog, Outcome, Record};
+use crate::config::{Config, StudentData};
+use crate::spreadsheet::{self, Column, Id};
+
+/// The worksheet name used when no sheet name is configured for the student data.
+const DEFAULT_SHEET_NAME: &str = "Attendance";
+
+/// The file formats attendance records can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Xlsx,
+}
+
+impl Format {
+    /// Determines the export format from the extension of `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownFormat` if the extension is neither `.csv` nor `.xlsx`.
+    pub fn from_path(path: &Path) -> Result<Format> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("csv") => Ok(Format::Csv),
+            Some("xlsx") => Ok(Format::Xlsx),
+            _ => Err(Error::UnknownFormat {
+                path: path.to_path_buf(),
+            }),
+        }
+    }
+}
+
+/// The values written for each record.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Id,
+    Name,
+    Date,
+    Time,
+    Outcome,
+}
+
+impl Field {
+    fn header(self) -> &'static str {
+        match self {
+            Field::Id => "ID",
+            Field::Name => "Name",
+            Field::Date => "Date",
+            Field::Time => "Time",
+            Field::Outcome => "Outcome",
+        }
+    }
+
+    fn text(self, record: &Record) -> String {
+        match self {
+            Field::Id => record.student_id.clone(),
+            Field::Name => record.student_name.clone().unwrap_or_default(),
+            Field::Date => record.timestamp.date().to_string(),
+            Field::Time => format!(
+                "{:02}:{:02}:{:02}",
+                record.timestamp.hour(),
+                record.timestamp.minute(),
+                record.timestamp.second()
+            ),
+            Field::Outcome => record.outcome.to_string(),
+        }
+    }
+}
+
+/// Parses a date written as YYYY-MM-DD.
+pub fn parse_date(text: &str) -> std::result::Result<Date, time::error::Parse> {
+    Date::parse(text.trim(), &Iso8601::DATE)
+}
+
+/// Exports the sign-ins in the attendance log next to the configuration file.
+///
+/// Only sign-ins that MyStudio recorded, may have recorded, that are queued to be sent to it or
+/// that have to be entered in it by hand are exported, once each, so that the export can be
+/// reconciled with MyStudio.
+///
+/// # Arguments
+///
+/// * `config` - The configuration, which the attendance log is found next to.
+/// * `from` - The first day to export.
+/// * `to` - The last day to export.
+/// * `student_id` - If `Some`, only sign-ins of this student are exported.
+/// * `path` - Where to write the file. The format is chosen by its extension.
+///
+/// # Returns
+///
+/// Returns the number of exported sign-ins.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidDateRange` if `from` is after `to`, `Error::UnknownFormat` if the format
+/// cannot be told from `path`, or an error if the log cannot be read or the file written.
+pub fn export(
+    config: &Config,
+    from: Date,
+    to: Date,
+    student_id: Option<&str>,
+    path: &Path,
+) -> Result<usize> {
+    if from > to {
+        return Err(Error::InvalidDateRange { from, to });
+    }
+
+    let format = Format::from_path(path)?;
+    let log = AttendanceLog::new(&config.path().with_file_name(attendance::FILE_NAME));
+    let records = sign_ins(log.query(from..=to, student_id)?);
+    write(&records, &config.student_data, format, path)?;
+
+    Ok(records.len())
+}
+
+/// Reduces attendance records to one record per sign-in that reached, or is on its way to,
+/// MyStudio.
+///
+/// A queued check-in is logged again with the same ID and time once it is replayed, so only the
+/// latest outcome of each sign-in is kept, in the place of its first record. Unknown IDs and
+/// failed check-ins are left out, while unconfirmed ones and the ones that were not synced are kept
+/// so that they can be checked.
+fn sign_ins(records: Vec<Record>) -> Vec<Record> {
+    let mut sign_ins: Vec<Record> = Vec::new();
+    let mut positions: HashMap<(Id, OffsetDateTime), usize> = HashMap::new();
+    for record in records {
+        match positions.entry((record.student_id.clone(), record.timestamp)) {
+            Entry::Occupied(position) => sign_ins[*position.get()] = record,
+            Entry::Vacant(position) => {
+                position.insert(sign_ins.len());
+                sign_ins.push(record);
+            }
+        }
+    }
+
+    sign_ins.retain(|record| {
+        matches!(
+            record.outcome,
+            Outcome::CheckedIn
+                | Outcome::Queued
+                | Outcome::Synced
+                | Outcome::NeedsReconciliation
+                | Outcome::Unconfirmed { .. }
+        )
+    });
+    sign_ins
+}
+
+/// Writes attendance records to a CSV or Excel file.
+///
+/// The ID and name are written to the same columns, or under the same headers, that
+/// `student_data` reads them from, so an export follows the same conventions as the roster. The
+/// date, time and outcome of each record fill the remaining columns from the left, and the first
+/// row holds column headers. CSV exports use the configured delimiter and character encoding, and
+/// Excel exports use the configured sheet name.
+///
+/// # Arguments
+///
+/// * `records` - The records to export, in the order they should appear.
+/// * `student_data` - The roster settings whose column conventions the export follows.
+/// * `format` - The file format to write.
+/// * `path` - Where to write the file. An existing file is overwritten.
+///
+/// # Errors
+///
+/// Returns `Error::Spreadsheet` if the configured delimiter or encoding is invalid, or an error if
+/// the file cannot be written.
+pub fn write(
+    records: &[Record],
+    student_data: &StudentData,
+    format: Format,
+    path: &Path,
+) -> Result<()> {
+    let columns = layout(student_data);
+
+    match format {
+        Format::Csv => write_csv(records, &columns, student_data, path),
+        Format::Xlsx => write_xlsx(records, &columns, &student_data.sheet_name, path),
+    }
+}
+
+/// Assigns a column index and a header to every field.
+///
+/// Columns that the roster maps by index keep that index. Columns that it maps by header are
+/// written with that header instead, so that the export can be read back with the same settings.
+fn layout(student_data: &StudentData) -> Vec<(usize, Field, String)> {
+    let mut columns = Vec::new();
+    place(&mut columns, Some(&student_data.id_column), Field::Id);
+    place(&mut columns, Some(&student_data.name_column), Field::Name);
+    place(&mut columns, None, Field::Date);
+    place(&mut columns, None, Field::Time);
+    place(&mut columns, None, Field::Outcome);
+
+    columns.sort_by_key(|(column, _, _)| *column);
+    columns
+}
+
+/// Places `field` in the `preferred` column, or in the lowest free column if that is taken or
+/// only known by header.
+fn place(columns: &mut Vec<(usize, Field, String)>, preferred: Option<&Column>, field: Field) {
+    let is_free = |column: &usize| columns.iter().all(|(taken, _, _)| taken != column);
+    let column = preferred
+        .and_then(Column::index)
+        .map(usize::from)
+        .filter(is_free)
+        .unwrap_or_else(|| (0..).find(is_free).unwrap());
+    let header = preferred
+        .and_then(Column::header)
+        .unwrap_or(field.header())
+        .to_owned();
+
+    columns.push((column, field, header));
+}
+
+/// Writes attendance records to a CSV file with the delimiter and encoding the roster is read
+/// with.
+///
+/// Characters the encoding cannot represent are written as HTML character references, e.g.
+/// `&#128512;`, and reported. UTF-16 cannot be written, so UTF-8 is written in its place.
+fn write_csv(
+    records: &[Record],
+    columns: &[(usize, Field, String)],
+    student_data: &StudentData,
+    path: &Path,
+) -> Result<()> {
+    let width = columns.last().map_or(0, |(column, _, _)| column + 1);
+    let delimiter = spreadsheet::csv_delimiter(student_data, b',')?;
+    let encoding = spreadsheet::csv_encoding(student_data)?;
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+    let mut row = vec![String::new(); width];
+    for (column, _, header) in columns {
+        row[*column] = header.clone();
+    }
+    writer.write_record(&row)?;
+    for record in records {
+        for (column, field, _) in columns {
+            row[*column] = field.text(record);
+        }
+        writer.write_record(&row)?;
+    }
+    let contents = writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))?;
+
+    // the csv writer only ever writes the text it was given, which is valid UTF-8
+    let contents = String::from_utf8_lossy(&contents);
+    let (bytes, encoding, had_errors) = encoding.encode(&contents);
+    if had_errors {
+        eprintln!(
+            "Some characters cannot be written in {}, they have been replaced",
+            encoding.name()
+        );
+    }
+    fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+fn write_xlsx(
+    records: &[Record],
+    columns: &[(usize, Field, String)],
+    sheet_name: &str,
+    path: &Path,
+) -> Result<()> {
+    let header_format = CellFormat::new().set_bold();
+    let date_format = CellFormat::new().set_num_format("yyyy-mm-dd");
+    let time_format = CellFormat::new().set_num_format("hh:mm:ss");
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(if sheet_name.is_empty() {
+        DEFAULT_SHEET_NAME
+    } else {
+        sheet_name
+    })?;
+
+    for (column, _, header) in columns {
+        worksheet.write_string_with_format(0, *column as u16, header, &header_format)?;
+    }
+
+    for (row, record) in (1..).zip(records) {
+        for (column, field, _) in columns {
+            let column = *column as u16;
+            match field {
+                Field::Date => {
+                    let date = record.timestamp.date();
+                    let value = ExcelDateTime::from_ymd(
+                        date.year() as u16,
+                        date.month().into(),
+                        date.day(),
+                    )?;
+                    worksheet.write_datetime_with_format(row, column, value, &date_format)?;
+                }
+                Field::Time => {
+                    let time = record.timestamp.time();
+                    let value =
+                        ExcelDateTime::from_hms(time.hour().into(), time.minute(), time.second())?;
+                    worksheet.write_datetime_with_format(row, column, value, &time_format)?;
+                }
+                _ => {
+                    worksheet.write_string(row, column, field.text(record))?;
+                }
+            }
+        }
+    }
+
+    worksheet.autofit();
+    workbook.save(path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::{Data, Reader, open_workbook_auto};
+    use tempfile::tempdir;
+    use time::Duration;
+
+    use super::*;
+
+    fn record(student_id: &str, minutes: i64, outcome: Outcome) -> Record {
+        Record {
+            timestamp: OffsetDateTime::UNIX_EPOCH + Duration::minutes(minutes),
+            student_id: student_id.to_owned(),
+            student_name: None,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn replayed_sign_in_keeps_its_first_place_and_latest_outcome() {
+        let records = vec![
+            record("00123", 0, Outcome::Queued),
+            record("00124", 1, Outcome::CheckedIn),
+            record("00123", 0, Outcome::Synced),
+        ];
+
+        assert_eq!(
+            sign_ins(records),
+            [
+                record("00123", 0, Outcome::Synced),
+                record("00124", 1, Outcome::CheckedIn),
+            ]
+        );
+    }
+
+    #[test]
+    fn sign_ins_at_different_times_are_kept_apart() {
+        let records = vec![
+            record("00123", 0, Outcome::CheckedIn),
+            record("00123", 1, Outcome::CheckedIn),
+        ];
+
+        assert_eq!(sign_ins(records.clone()), records);
+    }
+
+    #[test]
+    fn only_sign_ins_that_may_be_in_my_studio_are_kept() {
+        let unconfirmed = Outcome::Unconfirmed {
+            error: "timed out".to_owned(),
+        };
+        let records = vec![
+            record("00123", 0, Outcome::CheckedIn),
+            record("99999", 1, Outcome::UnknownId),
+            record("00124", 2, Outcome::NeedsReconciliation),
+            record("00125", 3, unconfirmed.clone()),
+            record(
+                "00126",
+                4,
+                Outcome::Failed {
+                    error: "rejected".to_owned(),
+                },
+            ),
+            record("00127", 5, Outcome::Queued),
+        ];
+
+        assert_eq!(
+            sign_ins(records),
+            [
+                record("00123", 0, Outcome::CheckedIn),
+                record("00124", 2, Outcome::NeedsReconciliation),
+                record("00125", 3, unconfirmed),
+                record("00127", 5, Outcome::Queued),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_export_uses_the_roster_delimiter_and_encoding() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("attendance.csv");
+        let student_data = StudentData {
+            delimiter: Some(';'),
+            encoding: Some("Shift_JIS".to_owned()),
+            ..StudentData::default()
+        };
+        let mut signed_in = record("00123", 0, Outcome::CheckedIn);
+        signed_in.student_name = Some("山田".to_owned());
+
+        write(&[signed_in], &student_data, Format::Csv, &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        let (contents, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(&bytes);
+        assert!(!had_errors);
+        assert_eq!(
+            contents,
+            "ID;Name;Date;Time;Outcome\n00123;山田;1970-01-01;00:00:00;Checked in\n"
+        );
+    }
+
+    #[test]
+    fn xlsx_export_can_be_read_back() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("attendance.xlsx");
+        let mut signed_in = record("00123", 90, Outcome::CheckedIn);
+        signed_in.student_name = Some("Aiko".to_owned());
+
+        write(&[signed_in], &StudentData::default(), Format::Xlsx, &path).unwrap();
+        let mut workbook = open_workbook_auto(&path).unwrap();
+        let sheet = workbook.worksheet_range(DEFAULT_SHEET_NAME).unwrap();
+        let rows: Vec<_> = sheet.rows().collect();
+
+        assert_eq!(rows.len(), 2);
+        let headers: Vec<_> = rows[0].iter().map(Data::to_string).collect();
+        assert_eq!(headers, ["ID", "Name", "Date", "Time", "Outcome"]);
+        assert_eq!(rows[1][0], Data::String("00123".to_owned()));
+        assert_eq!(rows[1][1], Data::String("Aiko".to_owned()));
+        // 1970-01-01 and 01:30 as Excel serial numbers
+        let [Data::DateTime(date), Data::DateTime(time)] = &rows[1][2..4] else {
+            panic!("the date and time were not written as dates");
+        };
+        assert_eq!(date.as_f64(), 25569.0);
+        assert_eq!(time.as_f64(), 0.0625);
+        assert_eq!(rows[1][4], Data::String("Checked in".to_owned()));
+    }
+
+    #[test]
+    fn unknown_extension_is_not_a_format() {
+        assert_eq!(
+            Format::from_path(Path::new("attendance.XLSX")).unwrap(),
+            Format::Xlsx
+        );
+        assert!(matches!(
+            Format::from_path(Path::new("attendance.ods")),
+            Err(Error::UnknownFormat { path }) if path == Path::new("attendance.ods")
+        ));
+        assert!(matches!(
+            Format::from_path(Path::new("attendance")),
+            Err(Error::UnknownFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn failed_replay_removes_the_queued_sign_in() {
+        let records = vec![
+            record("00123", 0, Outcome::Queued),
+            record(
+                "00123",
+                0,
+                Outcome::Failed {
+                    error: "rejected".to_owned(),
+                },
+            ),
+        ];
+
+        assert!(sign_ins(records).is_empty());
+    }
+}
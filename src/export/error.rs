@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Represents errors that can occur when exporting attendance records.
+///
+/// # Variants
+///
+/// * `Attendance` - Represents errors when reading the attendance log.
+/// * `Csv` - Represents errors when writing a CSV file.
+/// * `Spreadsheet` - Represents errors in the roster's CSV settings, which CSV exports follow.
+/// * `Io` - Represents I/O errors when writing a CSV file.
+/// * `Xlsx` - Represents errors when writing an Excel file.
+/// * `UnknownFormat` - Indicates that the export format could not be determined from the path.
+/// * `InvalidDateRange` - Indicates that the first day of a range is after the last day.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// An error when reading the attendance log, originating from the `attendance` module.
+    #[error(transparent)]
+    Attendance(#[from] crate::attendance::Error),
+
+    /// Represents errors when writing a CSV file, originating from the `csv` library.
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    /// Represents errors in the configured CSV delimiter or encoding, originating from the
+    /// `spreadsheet` module.
+    #[error(transparent)]
+    Spreadsheet(#[from] crate::spreadsheet::Error),
+
+    /// Represents I/O errors when writing a CSV file.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Represents errors when writing an Excel file, originating from the `rust_xlsxwriter`
+    /// library.
+    #[error(transparent)]
+    Xlsx(#[from] rust_xlsxwriter::XlsxError),
+
+    /// Indicates that the export format could not be determined from the file extension.
+    #[error("Cannot tell the export format of '{}'. Use a '.csv' or '.xlsx' extension.", path.display())]
+    UnknownFormat { path: PathBuf },
+
+    /// Indicates that the first day of a date range is after its last day.
+    #[error("The first day ({from}) is after the last day ({to}).")]
+    InvalidDateRange { from: time::Date, to: time::Date },
+}
@@ -1,42 +1,241 @@
 // Hide console window in Windows release builds. Ignored on other platforms.
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod attendance;
+mod check_in;
+mod cli;
 mod config;
+mod export;
 mod my_studio;
+mod offline_queue;
+mod pin_lockout;
+mod roster;
+mod scanner;
 mod scheduler;
+mod secrets;
 mod spreadsheet;
 mod ui;
 
-use std::{cell::RefCell, path::Path, process::exit, rc::Rc, time::Duration};
+use std::{cell::RefCell, collections::HashMap, path::Path, process::exit, rc::Rc, time::Duration};
 
+use clap::Parser;
 use slint::ComponentHandle;
-use tokio::runtime::Runtime;
+use time::OffsetDateTime;
+use tokio::{runtime::Runtime, sync::mpsc::unbounded_channel};
 
+use crate::attendance::AttendanceLog;
+use crate::check_in::Desk;
+use crate::cli::{Cli, Command, RosterCommand, TokenCommand};
+use crate::config::{Config, Location};
 use crate::my_studio::HttpClient;
+use crate::offline_queue::{OfflineQueue, Replayer};
+use crate::roster::RosterWatcher;
 use crate::scheduler::{Config as SchedulerConfig, Scheduler};
-use crate::spreadsheet::load_student_info_from_xlsx;
 
 fn main() {
+    let cli = Cli::parse();
+    if !matches!(cli.command, None | Some(Command::Run)) {
+        attach_console();
+    }
+
+    let location = config::locate(cli.config.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Error when locating config: {e}");
+        exit(1);
+    });
+    let config = Rc::new(RefCell::new(config::load(&location.path).unwrap_or_else(
+        |e| {
+            eprintln!(
+                "Error when loading config from '{}': {e}",
+                location.path.display()
+            );
+            exit(1);
+        },
+    )));
+    for warning in config.try_borrow().unwrap().warnings() {
+        eprintln!("Warning in '{}': {warning}", location.path.display());
+    }
+
+    let result = match cli.command.unwrap_or(Command::Run) {
+        Command::Run => {
+            // without credentials, sign-ins fail but are still logged, and the settings page
+            // stays available, so the desk is never left without a kiosk
+            if let Err(e) = attach_secret_store(&config) {
+                eprintln!("Error when loading credentials: {e}");
+            }
+            run_kiosk(&config, &location);
+            return;
+        }
+        Command::CheckIn(args) => attach_secret_store(&config)
+            .map_err(cli::Error::from)
+            .and_then(|()| cli::check_in(&config, &args))
+            .map(|record| {
+                let name = record.student_name.unwrap_or_default();
+                println!("Checked in {name} ({}).", record.student_id);
+            }),
+        Command::Roster(RosterCommand::Validate) => {
+            cli::validate_roster(&config.try_borrow().unwrap())
+        }
+        Command::Roster(RosterCommand::Show) => cli::show_roster(&config.try_borrow().unwrap()),
+        Command::Token(TokenCommand::Test) => attach_secret_store(&config)
+            .map_err(cli::Error::from)
+            .and_then(|()| cli::test_token(&config))
+            .map(|()| println!("A session token was acquired from MyStudio.")),
+        Command::Export(args) => cli::export(&config.try_borrow().unwrap(), &args).map(|count| {
+            println!("Exported {count} sign-ins to '{}'.", args.output.display());
+        }),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        exit(1);
+    }
+}
+
+/// Sends the output of a subcommand to the console it was started from.
+///
+/// Windows release builds are linked without a console so that none opens next to the kiosk
+/// window, which leaves the subcommands nowhere to print to unless they attach to one.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+
+    // this fails when there is no console to attach to, or when one is attached already
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+/// Opens the configured secret store and reads the credentials from it.
+///
+/// Only the commands that talk to MyStudio need credentials, so the store is not opened for the
+/// others.
+fn attach_secret_store(config: &Rc<RefCell<Config>>) -> Result<(), config::Error> {
+    let mut config = config.try_borrow_mut().unwrap();
+    let config_dir = config
+        .path()
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let store = secrets::open(&config.secrets.backend, &config_dir)?;
+    config.attach_secret_store(store)
+}
+
+/// Opens the kiosk window and runs it until it is closed.
+fn run_kiosk(config: &Rc<RefCell<Config>>, location: &Location) {
     let runtime = Runtime::new().unwrap();
-    let mut _scheduler = Scheduler::new(
+    // network requests are driven from the ui event loop, so they need to be able to find the
+    // runtime from the main thread
+    let runtime_handle = runtime.handle().clone();
+    let _runtime_guard = runtime_handle.enter();
+    let scheduler = Rc::new(RefCell::new(Scheduler::new(
         runtime,
         SchedulerConfig {
             max_poll_interval: Duration::from_secs(1),
             ..Default::default()
         },
-    );
+    )));
+
+    let roster = Rc::new(RefCell::new(HashMap::new()));
+    let roster_watcher = RosterWatcher::new(Rc::clone(config), Rc::clone(&roster));
 
-    let config = Rc::new(RefCell::new(
-        config::load(Path::new("config.toml")).unwrap_or_else(|e| {
-            eprintln!("Error when loading config from 'config.toml': {e}");
+    let client = HttpClient::new(Rc::clone(config));
+
+    let config_path = config.try_borrow().unwrap().path().to_path_buf();
+    let log = AttendanceLog::new(&config_path.with_file_name(attendance::FILE_NAME));
+
+    let offline_queue_path = config_path.with_file_name(offline_queue::FILE_NAME);
+    let offline_queue = Rc::new(RefCell::new(
+        OfflineQueue::open(&offline_queue_path).unwrap_or_else(|e| {
+            eprintln!(
+                "Error when loading offline check-ins from '{}': {e}",
+                offline_queue_path.display()
+            );
             exit(1);
         }),
     ));
+    let replayer = Replayer::start(&offline_queue, &client, &scheduler, &log);
 
-    let _database = load_student_info_from_xlsx(&config.try_borrow().unwrap()).unwrap();
+    let desk = Desk::new(roster, client.clone(), offline_queue, replayer, log);
 
-    let _client = HttpClient::new(Rc::clone(&config));
-
-    let ui = ui::init(&config);
+    let ui = ui::init(config, location, &desk, &roster_watcher);
+    roster_watcher.start();
+    schedule_session_refresh(&scheduler, &client);
     ui.run().unwrap();
 }
+
+/// How often the MyStudio session token is refreshed ahead of time, so that a long-running kiosk
+/// does not have to wait for a rejected check-in to notice that its token expired.
+const SESSION_REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// How long to wait before trying again when refreshing the session token failed.
+const SESSION_REFRESH_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Why the session token is refreshed.
+#[derive(Debug)]
+enum Refresh {
+    /// The refresh that is due every `SESSION_REFRESH_INTERVAL`.
+    Scheduled,
+    /// A retry scheduled after a failed refresh is due.
+    Retry,
+}
+
+/// Refreshes the session token of `client` every `SESSION_REFRESH_INTERVAL`.
+///
+/// The scheduler only runs `Send` tasks while the client lives on the ui thread, so the scheduled
+/// tasks just signal the ui event loop, which performs the refresh. A failed refresh is retried
+/// after `SESSION_REFRESH_RETRY_DELAY`, with at most one retry pending at a time, so that a long
+/// outage does not pile up retries.
+fn schedule_session_refresh(scheduler: &Rc<RefCell<Scheduler>>, client: &HttpClient) {
+    let (tx, mut rx) = unbounded_channel();
+    let signal = move |refresh: Refresh| {
+        let tx = tx.clone();
+        async move {
+            // the receiver only goes away when the ui has shut down
+            let _ = tx.send(refresh);
+        }
+    };
+
+    let scheduled = scheduler.borrow_mut().schedule_repeating(
+        OffsetDateTime::now_utc() + SESSION_REFRESH_INTERVAL,
+        SESSION_REFRESH_INTERVAL,
+        {
+            let signal = signal.clone();
+            move || signal(Refresh::Scheduled)
+        },
+    );
+    if let Err(e) = scheduled {
+        eprintln!("Error when scheduling session token refresh: {e}");
+        return;
+    }
+
+    let scheduler = Rc::clone(scheduler);
+    let client = client.clone();
+    slint::spawn_local(async move {
+        let mut retry_scheduled = false;
+
+        while let Some(refresh) = rx.recv().await {
+            if let Refresh::Retry = refresh {
+                retry_scheduled = false;
+            }
+
+            let Err(e) = client.aquire_session_token().await else {
+                continue;
+            };
+            eprintln!("Error when refreshing session token: {e}");
+            if retry_scheduled {
+                continue;
+            }
+
+            let retry = scheduler.borrow_mut().schedule(
+                OffsetDateTime::now_utc() + SESSION_REFRESH_RETRY_DELAY,
+                signal(Refresh::Retry),
+            );
+            match retry {
+                Ok(()) => retry_scheduled = true,
+                Err(e) => eprintln!("Error when scheduling session token refresh: {e}"),
+            }
+        }
+    })
+    .unwrap();
+}
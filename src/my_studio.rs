@@ -1,13 +1,26 @@
 mod error;
+/// A stand-in for the MyStudio API that tests can send requests to.
+#[cfg(test)]
+pub mod stub;
 
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
-use error::{ApiError, Error, Result};
-use reqwest::Client;
+pub use error::Error;
+use error::{ApiError, Result};
+use reqwest::{Client, StatusCode};
 use serde_json::{Value, json};
+use time::OffsetDateTime;
 
 use crate::config::Config;
 
+/// The base URL of the MyStudio API.
+const API_URL: &str = "https://cn.mystudio.io/Api/v2";
+/// How long to wait for a connection to the MyStudio API before treating it as unreachable.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a whole request may take before the MyStudio API is treated as unreachable, so that a
+/// check-in on a network that drops packets is queued instead of never finishing.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
 /// An HTTP client for interacting with the MyStudio API.
 ///
 /// This struct encapsulates the functionality needed to communicate with the MyStudio API,
@@ -17,24 +30,42 @@ use crate::config::Config;
 /// # Fields
 ///
 /// * `client` - A `reqwest::Client` instance used for making HTTP requests to the API.
+/// * `api_url` - The base URL that the API endpoints are appended to.
 /// * `config` - A shared, mutable reference to a `Config` struct containing authentication
 ///   credentials and other settings.
 /// * `session_token` - An optional String that stores the session token after successful
-///   authentication.
+///   authentication. It is shared between clones of the client so that a token acquired by
+///   one clone is reused by all of them.
 #[derive(Debug, Clone)]
 pub struct HttpClient {
     client: Client,
+    api_url: String,
     config: Rc<RefCell<Config>>,
-    session_token: Option<String>,
+    session_token: Rc<RefCell<Option<String>>>,
+}
+
+/// The outcome of a successful attendance check-in.
+///
+/// # Fields
+///
+/// * `student_id` - The ID of the student that was checked in.
+/// * `message` - The message returned by the MyStudio API alongside the `Success` status, or an
+///   empty string if there was none.
+/// * `checked_in_at` - The local time at which the check-in was confirmed by the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckIn {
+    pub student_id: String,
+    pub message: String,
+    pub checked_in_at: OffsetDateTime,
 }
 
 impl HttpClient {
     /// Creates a new HTTP client for interacting with the MyStudio API.
     ///
     /// This constructor initializes an `HttpClient` instance with the provided configuration.
-    /// It creates a new `reqwest::Client` for making HTTP requests and initializes the
-    /// session token as `None`. The session token will be populated after a successful login
-    /// or token acquisition.
+    /// It creates a new `reqwest::Client` for making HTTP requests, which gives up after
+    /// `CONNECT_TIMEOUT` and `REQUEST_TIMEOUT`, and initializes the session token as `None`.
+    /// The session token will be populated after a successful login or token acquisition.
     ///
     /// # Arguments
     ///
@@ -45,10 +76,27 @@ impl HttpClient {
     ///
     /// Returns a new `HttpClient` instance configured with the provided settings.
     pub fn new(config: Rc<RefCell<Config>>) -> HttpClient {
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            // this only fails where `Client::new` would panic, when no TLS backend is available
+            .unwrap();
+
         Self {
-            client: Client::new(),
+            client,
+            api_url: API_URL.to_owned(),
             config,
-            session_token: None,
+            session_token: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Creates a client that sends its requests to a stand-in for the MyStudio API.
+    #[cfg(test)]
+    pub fn with_api_url(config: Rc<RefCell<Config>>, api_url: &str) -> HttpClient {
+        Self {
+            api_url: api_url.to_owned(),
+            ..Self::new(config)
         }
     }
 
@@ -71,8 +119,8 @@ impl HttpClient {
     /// - `Error::Api` if the API response contains an error, such as:
     ///   - Missing or invalid fields in the response.
     ///   - An unrecognized value in the response.
-    pub async fn aquire_session_token(&mut self) -> Result<()> {
-        let request_url = "https://cn.mystudio.io/Api/v2/generateStudioAttendanceToken";
+    pub async fn aquire_session_token(&self) -> Result<()> {
+        let request_url = &format!("{}/generateStudioAttendanceToken", self.api_url);
         let request_body = &json!({
             "company_id": self.config.try_borrow()?.my_studio.company_id,
             "email": self.config.try_borrow()?.my_studio.email,
@@ -85,38 +133,363 @@ impl HttpClient {
             .json(request_body)
             .send()
             .await?
+            .error_for_status()?
             .json()
             .await?;
 
-        let status = response["status"].as_str().ok_or(ApiError::MissingField {
-            field: "status".to_owned(),
+        // the token is the message, so unlike other responses it cannot do without one
+        let token = parse_status(&response, request_url)?.ok_or(ApiError::MissingField {
+            field: "msg".to_owned(),
             url: request_url.to_owned(),
         })?;
+        *self.session_token.try_borrow_mut()? = Some(token.to_owned());
 
-        match status {
-            "Success" => {
-                let msg = response["msg"].as_str().ok_or(ApiError::MissingField {
-                    field: "msg".to_owned(),
-                    url: request_url.to_owned(),
-                })?;
-                self.session_token = Some(msg.to_string());
-                Ok(())
-            }
-            "Failed" => {
-                let msg = response["msg"].as_str().ok_or(ApiError::MissingField {
-                    field: "msg".to_owned(),
-                    url: request_url.to_owned(),
-                })?;
-                Err(Error::Api(ApiError::InvalidRequest {
-                    message: msg.to_owned(),
-                    url: request_url.to_owned(),
-                }))
+        Ok(())
+    }
+
+    /// Records the attendance of a student in MyStudio.
+    ///
+    /// This method sends a POST request to the MyStudio API to check in the student with the
+    /// given ID. If no session token has been acquired yet, one is requested first, and if the
+    /// stored token has expired, it is refreshed and the request is retried once.
+    ///
+    /// # Arguments
+    ///
+    /// * `student_id` - The ID of the student to check in, as found in the student data spreadsheet.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<CheckIn>` describing the recorded attendance if the student was
+    /// successfully checked in, or an `Error` if an error occurs during the request or
+    /// response handling.
+    ///
+    /// # Errors
+    ///
+    /// This method can return the following errors:
+    /// - `Error::Http` if an HTTP error occurs during the request.
+    /// - `Error::Json` if the response cannot be parsed as valid JSON.
+    /// - `Error::Api` if the API rejects the check-in or the response is malformed. This includes
+    ///   `ApiError::SessionExpired` if the token is still rejected after being refreshed.
+    /// - `Error::Session` if no session token could be acquired, so the check-in was not sent.
+    pub async fn check_in(&self, student_id: &str) -> Result<CheckIn> {
+        let request_url = &format!("{}/studioAttendanceCheckin", self.api_url);
+        let request_body = json!({
+            "company_id": self.config.try_borrow()?.my_studio.company_id,
+            "student_id": student_id,
+            "from_page": "attendance"
+        });
+
+        let response = self.post_with_session(request_url, request_body).await?;
+
+        let msg = parse_status(&response, request_url)?;
+
+        Ok(CheckIn {
+            student_id: student_id.to_owned(),
+            message: msg.unwrap_or_default().to_owned(),
+            checked_in_at: OffsetDateTime::now_local()
+                .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+        })
+    }
+
+    /// Sends an authenticated POST request to the MyStudio API.
+    ///
+    /// The session token is added to `request_body` under the `token` key. If the API rejects
+    /// the token, a new one is acquired and the request is retried once. The API rejects a token
+    /// with a 401 or 403 status, or with a `Failed` status whose message is about the token, see
+    /// `is_token_rejection`. Other failures are never retried, since the request might already have
+    /// been recorded.
+    ///
+    /// # Returns
+    ///
+    /// Returns the parsed JSON body of the response. Its `status` is not interpreted.
+    async fn post_with_session(&self, request_url: &str, request_body: Value) -> Result<Value> {
+        match self
+            .try_post_with_session(request_url, request_body.clone())
+            .await
+        {
+            Err(Error::Api(ApiError::SessionExpired { .. })) => {
+                self.aquire_session_token()
+                    .await
+                    .map_err(|e| Error::Session(Box::new(e)))?;
+                self.try_post_with_session(request_url, request_body).await
             }
-            _ => Err(Error::Api(ApiError::UnrecognizedValue {
-                field: "status".to_owned(),
-                value: status.to_owned(),
+            result => result,
+        }
+    }
+
+    async fn try_post_with_session(
+        &self,
+        request_url: &str,
+        mut request_body: Value,
+    ) -> Result<Value> {
+        request_body["token"] = Value::String(self.session_token().await?);
+
+        let response = self
+            .client
+            .post(request_url)
+            .json(&request_body)
+            .send()
+            .await?;
+
+        if matches!(
+            response.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return Err(Error::Api(ApiError::SessionExpired {
+                url: request_url.to_owned(),
+            }));
+        }
+
+        let response = response.error_for_status()?.json().await?;
+        if is_token_rejection(&response) {
+            return Err(Error::Api(ApiError::SessionExpired {
                 url: request_url.to_owned(),
-            })),
+            }));
         }
+
+        Ok(response)
+    }
+
+    /// Returns the current session token, acquiring one first if none is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Session` if a token has to be acquired and that fails.
+    async fn session_token(&self) -> Result<String> {
+        if self.session_token.try_borrow()?.is_none() {
+            self.aquire_session_token()
+                .await
+                .map_err(|e| Error::Session(Box::new(e)))?;
+        }
+
+        Ok(self.session_token.try_borrow()?.clone().unwrap_or_default())
+    }
+}
+
+/// Returns whether a response rejects the session token it was sent with.
+///
+/// Besides answering with a 401 or 403 status, MyStudio rejects an expired or unknown token with
+/// a `Failed` status and a message about the token, e.g. `"Invalid token"`. Such a request was not
+/// carried out, so unlike other failures it is safe to send again with a new token.
+fn is_token_rejection(response: &Value) -> bool {
+    response["status"].as_str() == Some("Failed")
+        && response["msg"]
+            .as_str()
+            .is_some_and(|msg| msg.to_lowercase().contains("token"))
+}
+
+/// Interprets the `status` and `msg` fields that every MyStudio API response contains.
+///
+/// # Arguments
+///
+/// * `response` - The parsed JSON body of the response.
+/// * `request_url` - The URL the request was sent to, used for error reporting.
+///
+/// # Returns
+///
+/// Returns the `msg` field if the status is `Success`, or `None` if it has no message. A
+/// `Success` without a message still means the request was carried out.
+///
+/// # Errors
+///
+/// - `ApiError::MissingField` if `status` is missing, or if the status is `Failed` and `msg` is
+///   missing. Either field is also missing if it is not a string.
+/// - `ApiError::InvalidRequest` if the status is `Failed`, carrying the API's message.
+/// - `ApiError::UnrecognizedValue` if the status is anything else.
+fn parse_status<'a>(response: &'a Value, request_url: &str) -> Result<Option<&'a str>> {
+    let status = response["status"].as_str().ok_or(ApiError::MissingField {
+        field: "status".to_owned(),
+        url: request_url.to_owned(),
+    })?;
+
+    let msg = || {
+        response["msg"].as_str().ok_or(ApiError::MissingField {
+            field: "msg".to_owned(),
+            url: request_url.to_owned(),
+        })
+    };
+
+    match status {
+        "Success" => Ok(response["msg"].as_str()),
+        "Failed" => Err(Error::Api(ApiError::InvalidRequest {
+            message: msg()?.to_owned(),
+            url: request_url.to_owned(),
+        })),
+        _ => Err(Error::Api(ApiError::UnrecognizedValue {
+            field: "status".to_owned(),
+            value: status.to_owned(),
+            url: request_url.to_owned(),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    use super::*;
+
+    /// Answers token requests with a token and check-ins with `check_in`.
+    async fn start_stub(check_in: Value) -> (HttpClient, UnboundedReceiver<stub::Request>) {
+        let (api_url, requests) = stub::start(move |request| match request.path.as_str() {
+            "/generateStudioAttendanceToken" => (
+                200,
+                json!({ "status": "Success", "msg": "token" }).to_string(),
+            ),
+            _ => (200, check_in.to_string()),
+        })
+        .await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+        (client, requests)
+    }
+
+    /// Answers token requests with `token-1`, `token-2` and so on, and the `n`th check-in with
+    /// `check_ins(n)`, counting from 0.
+    async fn start_counting_stub(
+        check_ins: impl Fn(usize) -> (u16, Value) + Send + 'static,
+    ) -> (HttpClient, UnboundedReceiver<stub::Request>) {
+        let tokens = AtomicUsize::new(0);
+        let sent = AtomicUsize::new(0);
+        let (api_url, requests) = stub::start(move |request| {
+            if request.path == "/generateStudioAttendanceToken" {
+                let n = tokens.fetch_add(1, Ordering::SeqCst) + 1;
+                return (
+                    200,
+                    json!({ "status": "Success", "msg": format!("token-{n}") }).to_string(),
+                );
+            }
+            let (status, body) = check_ins(sent.fetch_add(1, Ordering::SeqCst));
+            (status, body.to_string())
+        })
+        .await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+        (client, requests)
+    }
+
+    /// Returns the tokens the check-ins received by the stub were sent with.
+    fn check_in_tokens(requests: &mut UnboundedReceiver<stub::Request>) -> Vec<String> {
+        let mut tokens = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            if request.path == "/studioAttendanceCheckin" {
+                tokens.push(
+                    request.body["token"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_owned(),
+                );
+            }
+        }
+        tokens
+    }
+
+    fn success() -> (u16, Value) {
+        (200, json!({ "status": "Success", "msg": "ok" }))
+    }
+
+    #[tokio::test]
+    async fn rejected_token_is_replaced_and_the_check_in_sent_again() {
+        let (client, mut requests) = start_counting_stub(|n| match n {
+            0 => (401, json!({})),
+            _ => success(),
+        })
+        .await;
+
+        let result = client.check_in("00123").await;
+
+        assert!(result.is_ok());
+        assert_eq!(check_in_tokens(&mut requests), ["token-1", "token-2"]);
+    }
+
+    #[tokio::test]
+    async fn token_rejected_twice_is_not_replaced_again() {
+        let (client, mut requests) = start_counting_stub(|_| (401, json!({}))).await;
+
+        let result = client.check_in("00123").await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Api(ApiError::SessionExpired { .. }))
+        ));
+        assert_eq!(check_in_tokens(&mut requests), ["token-1", "token-2"]);
+    }
+
+    #[tokio::test]
+    async fn server_error_is_not_sent_again() {
+        let (client, mut requests) = start_counting_stub(|_| (503, json!({}))).await;
+
+        let result = client.check_in("00123").await;
+
+        assert!(result.is_err_and(|e| e.is_unconfirmed()));
+        assert_eq!(check_in_tokens(&mut requests), ["token-1"]);
+    }
+
+    #[tokio::test]
+    async fn token_rejected_in_the_body_is_replaced() {
+        let (client, mut requests) = start_counting_stub(|n| match n {
+            0 => (200, json!({ "status": "Failed", "msg": "Invalid Token" })),
+            _ => success(),
+        })
+        .await;
+
+        let result = client.check_in("00123").await;
+
+        assert!(result.is_ok());
+        assert_eq!(check_in_tokens(&mut requests), ["token-1", "token-2"]);
+    }
+
+    #[tokio::test]
+    async fn other_failure_is_final() {
+        let (client, mut requests) = start_counting_stub(|_| {
+            (
+                200,
+                json!({ "status": "Failed", "msg": "Student not found" }),
+            )
+        })
+        .await;
+
+        let result = client.check_in("00123").await;
+
+        assert!(result.is_err_and(|e| e.is_rejection()));
+        assert_eq!(check_in_tokens(&mut requests), ["token-1"]);
+    }
+
+    #[tokio::test]
+    async fn success_without_a_message_is_a_check_in() {
+        let (client, _requests) = start_stub(json!({ "status": "Success" })).await;
+
+        let check_in = client.check_in("00123").await.unwrap();
+
+        assert_eq!(check_in.student_id, "00123");
+        assert_eq!(check_in.message, "");
+    }
+
+    #[tokio::test]
+    async fn failure_without_a_message_is_malformed() {
+        let (client, _requests) = start_stub(json!({ "status": "Failed" })).await;
+
+        let result = client.check_in("00123").await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Api(ApiError::MissingField { field, .. })) if field == "msg"
+        ));
+    }
+
+    #[tokio::test]
+    async fn token_response_without_a_token_is_malformed() {
+        let (api_url, _requests) =
+            stub::start(|_| (200, json!({ "status": "Success" }).to_string())).await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+
+        let result = client.aquire_session_token().await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Api(ApiError::MissingField { field, .. })) if field == "msg"
+        ));
+        assert_eq!(*client.session_token.borrow(), None);
     }
 }
@@ -14,6 +14,11 @@ pub enum Error {
     #[error(transparent)]
     Borrow(#[from] std::cell::BorrowError),
 
+    /// A borrowing error that occurs when attempting to mutably borrow from a `RefCell` that is already borrowed,
+    /// originating from the Rust standard library.
+    #[error(transparent)]
+    BorrowMut(#[from] std::cell::BorrowMutError),
+
     /// An HTTP error that occurred during a request, originating from the `reqwest` library.
     #[error(transparent)]
     Http(#[from] reqwest::Error),
@@ -21,6 +26,63 @@ pub enum Error {
     /// A JSON parsing error, originating from the `serde_json` library.
     #[error(transparent)]
     Json(#[from] serde_json::Error),
+
+    /// An error when acquiring the session token a request needed, so the request itself was never
+    /// sent.
+    #[error("Cannot acquire a session token: {0}")]
+    Session(Box<Error>),
+}
+
+impl Error {
+    /// Whether the request never reached the MyStudio API, so sending it again later cannot record
+    /// it twice.
+    ///
+    /// This is the case while the network is down or the API refuses connections, and when the
+    /// session token the request needed could not be acquired.
+    pub fn is_unreachable(&self) -> bool {
+        match self {
+            Error::Http(e) => e.is_connect(),
+            Error::Session(e) => matches!(**e, Error::Http(_)),
+            _ => false,
+        }
+    }
+
+    /// Whether the request reached the MyStudio API but its answer was lost, so it may have been
+    /// recorded even though it failed.
+    ///
+    /// This is the case when the request timed out after connecting, the connection broke while it
+    /// was sent, or the API failed with a 5xx status. Sending such a request again could record it
+    /// twice. An unreadable answer is not counted, since it usually comes from a captive portal
+    /// rather than from MyStudio.
+    pub fn is_unconfirmed(&self) -> bool {
+        match self {
+            Error::Http(e) => {
+                !e.is_connect()
+                    && (e.is_timeout()
+                        || e.is_request()
+                        || e.is_body()
+                        || e.status().is_some_and(|status| status.is_server_error()))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the API explicitly rejected the request, so sending it again would only be rejected
+    /// again.
+    ///
+    /// Only the answer to the request itself counts. Failing to acquire a session token for it, a
+    /// rejected token and responses that are not an answer from the API at all, such as a captive
+    /// portal page or a 429, are not rejections.
+    pub fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            Error::Api(
+                ApiError::InvalidRequest { .. }
+                    | ApiError::MissingField { .. }
+                    | ApiError::UnrecognizedValue { .. }
+            )
+        )
+    }
 }
 
 /// Represents API-specific errors that can occur in the `my_studio` module.
@@ -38,6 +100,9 @@ pub enum Error {
 ///   - `field` - The name of the missing or invalid field.
 ///   - `url` - The URL of the API endpoint that returned the response.
 ///
+/// * `SessionExpired` - Indicates that the session token was rejected by the API.
+///   - `url` - The URL of the API endpoint that rejected the token.
+///
 /// * `UnrecognizedValue` - Represents an unrecognized or unexpected value in the API response.
 ///   - `field` - The name of the field containing the unrecognized value.
 ///   - `value` - The unrecognized value.
@@ -65,6 +130,10 @@ pub enum ApiError {
     #[error("Missing or invalid field '{field}' in response from call to {url}.")]
     MissingField { field: String, url: String },
 
+    /// Indicates that the session token was rejected by the API, usually because it expired.
+    #[error("Session token was rejected by call to {url}.")]
+    SessionExpired { url: String },
+
     /// Represents an unrecognized or unexpected value in the API response.
     #[error("Unrecognized value '{value}' for field '{field}' in response from call to {url}.")]
     UnrecognizedValue {
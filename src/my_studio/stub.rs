@@ -0,0 +1,93 @@
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{UnboundedReceiver, unbounded_channel},
+};
+
+/// A request received by the stub.
+///
+/// # Fields
+///
+/// * `path` - The path the request was sent to, e.g. `"/studioAttendanceCheckin"`.
+/// * `body` - The JSON body of the request, or `Value::Null` if it is not JSON.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub path: String,
+    pub body: Value,
+}
+
+/// Starts answering requests on a local port.
+///
+/// # Arguments
+///
+/// * `respond` - Returns the status code and body of the response to a request.
+///
+/// # Returns
+///
+/// Returns the URL to pass to `HttpClient::with_api_url`, and a receiver of every request the
+/// stub answered, in order.
+pub async fn start(
+    respond: impl Fn(&Request) -> (u16, String) + Send + 'static,
+) -> (String, UnboundedReceiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let request = read_request(&mut stream).await;
+            let (status, body) = respond(&request);
+            let _ = tx.send(request);
+
+            let response = format!(
+                "HTTP/1.1 {status} Stub\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+
+    (url, rx)
+}
+
+/// Reads an HTTP request from `stream`.
+async fn read_request(stream: &mut TcpStream) -> Request {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    loop {
+        let read = stream.read(&mut buffer).await.unwrap();
+        request.extend_from_slice(&buffer[..read]);
+
+        let text = String::from_utf8_lossy(&request);
+        let Some(head_length) = text.find("\r\n\r\n") else {
+            if read == 0 {
+                return Request {
+                    path: String::new(),
+                    body: Value::Null,
+                };
+            }
+            continue;
+        };
+        let head = &text[..head_length];
+        let content_length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        let body_start = head_length + 4;
+        if request.len() >= body_start + content_length || read == 0 {
+            return Request {
+                path: head
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_owned(),
+                body: serde_json::from_slice(&request[body_start..]).unwrap_or_default(),
+            };
+        }
+    }
+}
@@ -0,0 +1,565 @@
+mod error;
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use error::Result;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+use crate::attendance::{AttendanceLog, Outcome, Record};
+use crate::my_studio::HttpClient;
+use crate::scheduler::Scheduler;
+use crate::spreadsheet::Id;
+
+/// The name of the queue file, which is stored next to the configuration file.
+pub const FILE_NAME: &str = "offline_queue.jsonl";
+
+/// How long to wait before the first retry after the MyStudio API could not be reached.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(15);
+/// Upper bound for the retry delay, which doubles after every failed attempt.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// A check-in that could not be sent to MyStudio when it was made.
+///
+/// # Fields
+///
+/// * `student_id` - The ID of the student that checked in.
+/// * `student_name` - The name the ID resolved to when the student checked in.
+/// * `recorded_at` - When the student checked in at the kiosk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedCheckIn {
+    pub student_id: Id,
+    #[serde(default)]
+    pub student_name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub recorded_at: OffsetDateTime,
+}
+
+/// A durable, first-in first-out queue of check-ins waiting to be sent to MyStudio.
+///
+/// The queue is stored as one JSON object per line. New check-ins are appended and synced to disk
+/// before `push` returns, so that no check-in is lost if the kiosk loses power while offline.
+///
+/// # Fields
+///
+/// * `path` - The path of the queue file.
+/// * `entries` - The queued check-ins, oldest first.
+#[derive(Debug)]
+pub struct OfflineQueue {
+    path: PathBuf,
+    entries: VecDeque<QueuedCheckIn>,
+}
+
+impl OfflineQueue {
+    /// Opens the queue stored at `path`, or creates an empty queue if the file does not exist.
+    ///
+    /// Lines that cannot be parsed, such as a partially written last line after a crash, are
+    /// reported and skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the file exists but cannot be read.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut entries = VecDeque::new();
+
+        if path.exists() {
+            for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(&line) {
+                    Ok(entry) => entries.push_back(entry),
+                    Err(e) => eprintln!(
+                        "Skipping unreadable entry on line {} of '{}': {e}",
+                        index + 1,
+                        path.display()
+                    ),
+                }
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Returns `true` if there are no check-ins waiting to be sent.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Appends a check-in to the queue and syncs it to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the check-in cannot be serialized or written, in which case it is not
+    /// added to the queue.
+    pub fn push(&mut self, entry: QueuedCheckIn) -> Result<()> {
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        self.entries.push_back(entry);
+        Ok(())
+    }
+
+    /// Removes the oldest queued check-in and persists the shortened queue.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue file cannot be rewritten, in which case the check-in stays
+    /// in the queue.
+    pub fn pop_front(&mut self) -> Result<Option<QueuedCheckIn>> {
+        let entry = self.entries.pop_front();
+        if let Err(e) = self.persist() {
+            if let Some(entry) = entry {
+                self.entries.push_front(entry);
+            }
+            return Err(e);
+        }
+
+        Ok(entry)
+    }
+
+    /// Puts a check-in back at the front of the queue and persists it, such as when it could not be
+    /// sent after it was removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue file cannot be rewritten. The check-in stays at the front of
+    /// the queue in memory regardless, so it is written with the next change to the queue.
+    pub fn push_front(&mut self, entry: QueuedCheckIn) -> Result<()> {
+        self.entries.push_front(entry);
+        self.persist()
+    }
+
+    /// Rewrites the queue file from `entries`, replacing the old file atomically.
+    fn persist(&self) -> Result<()> {
+        let temp_path = self.path.with_extension("jsonl.tmp");
+
+        let mut file = File::create(&temp_path)?;
+        for entry in &self.entries {
+            serde_json::to_writer(&mut file, entry)?;
+            file.write_all(b"\n")?;
+        }
+        file.sync_all()?;
+        fs::rename(temp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Signal {
+    /// Send the queued check-ins now.
+    Replay,
+    /// A retry scheduled after a failed replay is due.
+    Retry,
+}
+
+/// Sends queued check-ins to MyStudio in the background.
+///
+/// The replayer runs on the ui event loop, since the `HttpClient` cannot leave it. While the API is
+/// unreachable, retries are scheduled on the `Scheduler` with an exponential backoff between
+/// `INITIAL_RETRY_DELAY` and `MAX_RETRY_DELAY`.
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    tx: UnboundedSender<Signal>,
+}
+
+impl Replayer {
+    /// Starts replaying `queue` on the ui event loop.
+    ///
+    /// A first replay is requested immediately so that check-ins queued before the application was
+    /// last closed are sent as soon as possible. The outcome of every replayed check-in is
+    /// appended to `log`.
+    pub fn start(
+        queue: &Rc<RefCell<OfflineQueue>>,
+        client: &HttpClient,
+        scheduler: &Rc<RefCell<Scheduler>>,
+        log: &AttendanceLog,
+    ) -> Self {
+        let (tx, mut rx) = unbounded_channel();
+        let replayer = Self { tx: tx.clone() };
+
+        let queue = Rc::clone(queue);
+        let client = client.clone();
+        let scheduler = Rc::clone(scheduler);
+        let log = log.clone();
+        slint::spawn_local(async move {
+            let mut retry_delay = INITIAL_RETRY_DELAY;
+            let mut retry_scheduled = false;
+
+            while let Some(signal) = rx.recv().await {
+                if let Signal::Retry = signal {
+                    retry_scheduled = false;
+                }
+
+                match replay(&queue, &client, &log).await {
+                    Ok(()) => retry_delay = INITIAL_RETRY_DELAY,
+                    Err(e) => {
+                        eprintln!("Error when sending queued check-ins: {e}");
+                        if retry_scheduled {
+                            continue;
+                        }
+
+                        let tx = tx.clone();
+                        let scheduled = scheduler.borrow_mut().schedule(
+                            OffsetDateTime::now_utc() + retry_delay,
+                            async move {
+                                // the receiver only goes away when the ui has shut down
+                                let _ = tx.send(Signal::Retry);
+                            },
+                        );
+                        match scheduled {
+                            Ok(()) => {
+                                retry_scheduled = true;
+                                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                            }
+                            Err(e) => eprintln!("Error when scheduling queued check-ins: {e}"),
+                        }
+                    }
+                }
+            }
+        })
+        .unwrap();
+
+        replayer.replay();
+        replayer
+    }
+
+    /// Requests that the queued check-ins are sent as soon as possible.
+    pub fn replay(&self) {
+        // the receiver only goes away when the ui has shut down
+        let _ = self.tx.send(Signal::Replay);
+    }
+}
+
+#[cfg(test)]
+impl Replayer {
+    /// Returns a replayer that is not attached to a queue, so replay requests are ignored.
+    pub fn detached() -> Self {
+        let (tx, _) = unbounded_channel();
+        Self { tx }
+    }
+}
+
+/// Sends queued check-ins in order until the queue is empty or one of them cannot be sent.
+///
+/// Each check-in is removed from the queue file before it is sent, so that a check-in MyStudio has
+/// recorded is never sent again. Check-ins that the API explicitly rejects are dropped, since they
+/// would be rejected again on every retry, and so are check-ins that reached the API without being
+/// confirmed, since sending them again could record them twice. Any other failure, such as an
+/// unreachable API, a session token that cannot be acquired, a 429 or a captive portal page in
+/// place of the API's answer, puts the check-in back at the front of the queue and ends the
+/// replay, so that it is retried after a backoff. An unreadable answer that did come from MyStudio
+/// is retried as well, at the risk of recording that check-in twice.
+///
+/// MyStudio is not told when a check-in was made, so it credits the attendance when the check-in
+/// is sent. Check-ins made on an earlier day would be credited to the wrong day, so they are not
+/// sent but logged as `Outcome::NeedsReconciliation`, to be entered in MyStudio by hand. The
+/// attendance log keeps the time the student signed in at the kiosk.
+async fn replay(
+    queue: &RefCell<OfflineQueue>,
+    client: &HttpClient,
+    log: &AttendanceLog,
+) -> Result<()> {
+    loop {
+        let Some(entry) = queue.borrow_mut().pop_front()? else {
+            return Ok(());
+        };
+
+        let today = OffsetDateTime::now_local()
+            .unwrap_or_else(|_| OffsetDateTime::now_utc())
+            .to_offset(entry.recorded_at.offset())
+            .date();
+        let outcome = if entry.recorded_at.date() < today {
+            eprintln!(
+                "Not sending the queued check-in of '{}' recorded at {}, it has to be entered in \
+                 MyStudio by hand",
+                entry.student_id, entry.recorded_at
+            );
+            Outcome::NeedsReconciliation
+        } else {
+            match client.check_in(&entry.student_id).await {
+                Ok(_) => Outcome::Synced,
+                Err(e) if e.is_rejection() => {
+                    eprintln!(
+                        "Dropping queued check-in of '{}' recorded at {}: {e}",
+                        entry.student_id, entry.recorded_at
+                    );
+                    Outcome::Failed {
+                        error: e.to_string(),
+                    }
+                }
+                Err(e) if e.is_unconfirmed() => {
+                    eprintln!(
+                        "MyStudio did not confirm the queued check-in of '{}' recorded at {}, \
+                         it is not sent again: {e}",
+                        entry.student_id, entry.recorded_at
+                    );
+                    Outcome::Unconfirmed {
+                        error: e.to_string(),
+                    }
+                }
+                Err(e) => {
+                    queue.borrow_mut().push_front(entry)?;
+                    return Err(e.into());
+                }
+            }
+        };
+
+        let record = Record {
+            timestamp: entry.recorded_at,
+            student_id: entry.student_id,
+            student_name: Some(entry.student_name),
+            outcome,
+        };
+        if let Err(e) = log.append(&record) {
+            eprintln!(
+                "Error when logging check-in of '{}': {e}",
+                record.student_id
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::tempdir;
+    use time::Date;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::my_studio::stub;
+
+    fn queued(student_id: &str) -> QueuedCheckIn {
+        QueuedCheckIn {
+            student_id: student_id.to_owned(),
+            student_name: format!("Student {student_id}"),
+            recorded_at: OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+        }
+    }
+
+    #[test]
+    fn queue_survives_reopening() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let mut queue = OfflineQueue::open(&path).unwrap();
+        for student_id in ["00123", "00124", "00125"] {
+            queue.push(queued(student_id)).unwrap();
+        }
+
+        let first = queue.pop_front().unwrap().unwrap();
+        let reopened = OfflineQueue::open(&path).unwrap();
+
+        assert_eq!(first.student_id, "00123");
+        assert_eq!(reopened.entries, queue.entries);
+        assert_eq!(
+            reopened
+                .entries
+                .iter()
+                .map(|entry| entry.student_id.as_str())
+                .collect::<Vec<_>>(),
+            ["00124", "00125"]
+        );
+    }
+
+    #[test]
+    fn entry_put_back_stays_first_after_reopening() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let mut queue = OfflineQueue::open(&path).unwrap();
+        queue.push(queued("00123")).unwrap();
+        queue.push(queued("00124")).unwrap();
+
+        let first = queue.pop_front().unwrap().unwrap();
+        queue.push_front(first.clone()).unwrap();
+        let reopened = OfflineQueue::open(&path).unwrap();
+
+        assert_eq!(reopened.entries.front(), Some(&first));
+        assert_eq!(reopened.entries.len(), 2);
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped_when_opening() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let mut queue = OfflineQueue::open(&path).unwrap();
+        queue.push(queued("00123")).unwrap();
+        // a line cut short by a crash while it was appended
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"student_id":"001"#).unwrap();
+
+        let reopened = OfflineQueue::open(&path).unwrap();
+
+        assert_eq!(reopened.entries, queue.entries);
+    }
+
+    #[test]
+    fn empty_queue_pops_nothing() {
+        let dir = tempdir().unwrap();
+        let mut queue = OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap();
+
+        let popped = queue.pop_front().unwrap();
+
+        assert!(queue.is_empty());
+        assert_eq!(popped, None);
+    }
+
+    #[tokio::test]
+    async fn captive_portal_page_keeps_check_ins_queued() {
+        let dir = tempdir().unwrap();
+        let queue = RefCell::new(OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap());
+        queue.borrow_mut().push(queued("1")).unwrap();
+        queue.borrow_mut().push(queued("2")).unwrap();
+
+        let (api_url, mut requests) = stub::start(|_| {
+            (
+                200,
+                "<html><body>Accept the terms to use the Wi-Fi</body></html>".to_owned(),
+            )
+        })
+        .await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+        let log = AttendanceLog::new(&dir.path().join("attendance.jsonl"));
+
+        let result = replay(&queue, &client, &log).await;
+        let reopened = OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(reopened.entries, queue.borrow().entries);
+        assert_eq!(queue.borrow().entries.len(), 2);
+        assert_eq!(queue.borrow().entries[0].student_id, "1");
+        // the token request fails, so no check-in is sent and nothing is logged
+        assert_eq!(
+            requests.recv().await.unwrap().path,
+            "/generateStudioAttendanceToken"
+        );
+        assert!(requests.try_recv().is_err());
+        assert!(!dir.path().join("attendance.jsonl").exists());
+    }
+
+    #[tokio::test]
+    async fn rate_limited_check_in_stays_queued() {
+        let dir = tempdir().unwrap();
+        let queue = RefCell::new(OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap());
+        queue.borrow_mut().push(queued("1")).unwrap();
+
+        let (api_url, _requests) = stub::start(|request| {
+            if request.path == "/studioAttendanceCheckin" {
+                (429, String::new())
+            } else {
+                (
+                    200,
+                    json!({ "status": "Success", "msg": "token" }).to_string(),
+                )
+            }
+        })
+        .await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+        let log = AttendanceLog::new(&dir.path().join("attendance.jsonl"));
+
+        let result = replay(&queue, &client, &log).await;
+        let reopened = OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(reopened.entries.len(), 1);
+        assert_eq!(queue.borrow().entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn check_in_whose_token_keeps_being_rejected_stays_queued() {
+        let dir = tempdir().unwrap();
+        let queue = RefCell::new(OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap());
+        queue.borrow_mut().push(queued("1")).unwrap();
+
+        let (api_url, _requests) = stub::start(|request| {
+            let body = if request.path == "/studioAttendanceCheckin" {
+                json!({ "status": "Failed", "msg": "Invalid token" })
+            } else {
+                json!({ "status": "Success", "msg": "token" })
+            };
+            (200, body.to_string())
+        })
+        .await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+        let log = AttendanceLog::new(&dir.path().join("attendance.jsonl"));
+
+        let result = replay(&queue, &client, &log).await;
+
+        assert!(result.is_err());
+        assert_eq!(queue.borrow().entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejected_check_in_is_dropped_and_the_rest_are_sent() {
+        let dir = tempdir().unwrap();
+        let queue = RefCell::new(OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap());
+        queue.borrow_mut().push(queued("1")).unwrap();
+        queue.borrow_mut().push(queued("2")).unwrap();
+
+        let (api_url, _requests) =
+            stub::start(|request| match request.body["student_id"].as_str() {
+                Some("1") => (
+                    200,
+                    json!({ "status": "Failed", "msg": "Unknown student" }).to_string(),
+                ),
+                _ => (200, json!({ "status": "Success", "msg": "ok" }).to_string()),
+            })
+            .await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+        let log = AttendanceLog::new(&dir.path().join("attendance.jsonl"));
+
+        let result = replay(&queue, &client, &log).await;
+        let reopened = OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap();
+        let records = log.query(Date::MIN..=Date::MAX, None).unwrap();
+
+        assert!(result.is_ok());
+        assert!(reopened.is_empty());
+        assert!(matches!(records[0].outcome, Outcome::Failed { .. }));
+        assert_eq!(records[1].outcome, Outcome::Synced);
+    }
+
+    #[tokio::test]
+    async fn check_in_from_an_earlier_day_is_not_sent() {
+        let dir = tempdir().unwrap();
+        let queue = RefCell::new(OfflineQueue::open(&dir.path().join(FILE_NAME)).unwrap());
+        let mut yesterday = queued("1");
+        yesterday.recorded_at -= time::Duration::days(1);
+        queue.borrow_mut().push(yesterday.clone()).unwrap();
+
+        let (api_url, mut requests) =
+            stub::start(|_| (200, json!({ "status": "Success", "msg": "ok" }).to_string())).await;
+        let client = HttpClient::with_api_url(Rc::new(RefCell::new(Config::default())), &api_url);
+        let log = AttendanceLog::new(&dir.path().join("attendance.jsonl"));
+
+        let result = replay(&queue, &client, &log).await;
+        let records = log.query(Date::MIN..=Date::MAX, None).unwrap();
+
+        assert!(result.is_ok());
+        assert!(queue.borrow().is_empty());
+        assert!(requests.try_recv().is_err());
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].outcome, Outcome::NeedsReconciliation);
+        assert_eq!(records[0].timestamp, yesterday.recorded_at);
+    }
+}
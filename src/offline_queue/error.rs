@@ -0,0 +1,28 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Represents errors that can occur in the `offline_queue` module.
+///
+/// # Variants
+///
+/// * `Io` - Represents I/O errors that occur while reading or writing the queue file.
+/// * `Json` - Represents errors when serializing a queued check-in to JSON.
+/// * `MyStudio` - Represents errors when sending a queued check-in to the MyStudio API.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Represents I/O errors that occur during file operations.
+    ///
+    /// This variant wraps `std::io::Error` and is used when the queue file cannot be read,
+    /// appended to or rewritten.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// A JSON serialization error, originating from the `serde_json` library.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// An error when sending a queued check-in, originating from the `my_studio` module.
+    #[error(transparent)]
+    MyStudio(#[from] crate::my_studio::Error),
+}
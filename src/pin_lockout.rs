@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+/// How many wrong PINs can be entered in a row before the prompt is locked.
+const FREE_ATTEMPTS: u32 = 3;
+/// How long the prompt is locked after the first wrong PIN past `FREE_ATTEMPTS`. Every further
+/// wrong PIN doubles it.
+const BASE_LOCKOUT: Duration = Duration::from_secs(30);
+/// The longest the prompt is ever locked for.
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// The result of entering a PIN.
+///
+/// # Variants
+///
+/// * `Accepted` - The PIN is correct.
+/// * `Wrong` - The PIN is wrong.
+/// * `Locked` - The PIN was not checked, or was wrong and locked the prompt, because too many
+///   wrong PINs were entered. Holds how long until another PIN can be entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attempt {
+    Accepted,
+    Wrong,
+    Locked(Duration),
+}
+
+/// Limits how quickly PINs can be guessed.
+///
+/// After `FREE_ATTEMPTS` wrong PINs in a row, every further wrong PIN locks the prompt for twice
+/// as long as the one before, up to `MAX_LOCKOUT`. A correct PIN resets the count. The count is
+/// only kept while the application runs, which in kiosk mode cannot be closed without the PIN.
+///
+/// # Fields
+///
+/// * `failures` - The wrong PINs entered since the last correct one.
+/// * `locked_until` - When another PIN can be entered, if the prompt is locked.
+#[derive(Debug, Default)]
+pub struct PinLockout {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+impl PinLockout {
+    /// Checks a PIN, unless the prompt is locked.
+    ///
+    /// # Arguments
+    ///
+    /// * `at` - When the PIN was entered.
+    /// * `verify` - Returns whether the PIN is correct. It is not called while the prompt is
+    ///   locked.
+    pub fn attempt(&mut self, at: Instant, verify: impl FnOnce() -> bool) -> Attempt {
+        if let Some(locked_until) = self.locked_until
+            && locked_until > at
+        {
+            return Attempt::Locked(locked_until - at);
+        }
+
+        if verify() {
+            *self = Self::default();
+            return Attempt::Accepted;
+        }
+
+        self.failures += 1;
+        let Some(past_free) = self.failures.checked_sub(FREE_ATTEMPTS + 1) else {
+            return Attempt::Wrong;
+        };
+        let lockout = BASE_LOCKOUT
+            .checked_mul(2_u32.saturating_pow(past_free))
+            .map_or(MAX_LOCKOUT, |lockout| lockout.min(MAX_LOCKOUT));
+        self.locked_until = Some(at + lockout);
+        Attempt::Locked(lockout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrong(lockout: &mut PinLockout, at: Instant) -> Attempt {
+        lockout.attempt(at, || false)
+    }
+
+    #[test]
+    fn first_wrong_pins_are_not_locked() {
+        let mut lockout = PinLockout::default();
+        let now = Instant::now();
+
+        for _ in 0..FREE_ATTEMPTS {
+            assert_eq!(wrong(&mut lockout, now), Attempt::Wrong);
+        }
+        assert_eq!(wrong(&mut lockout, now), Attempt::Locked(BASE_LOCKOUT));
+    }
+
+    #[test]
+    fn pins_are_not_checked_while_locked() {
+        let mut lockout = PinLockout::default();
+        let now = Instant::now();
+        for _ in 0..=FREE_ATTEMPTS {
+            wrong(&mut lockout, now);
+        }
+
+        let later = now + Duration::from_secs(10);
+        let attempt = lockout.attempt(later, || panic!("the PIN was checked while locked"));
+
+        assert_eq!(
+            attempt,
+            Attempt::Locked(BASE_LOCKOUT - Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn lockout_doubles_up_to_the_maximum() {
+        let mut lockout = PinLockout::default();
+        let mut at = Instant::now();
+        for _ in 0..FREE_ATTEMPTS {
+            wrong(&mut lockout, at);
+        }
+
+        let mut lockouts = Vec::new();
+        for _ in 0..8 {
+            let Attempt::Locked(duration) = wrong(&mut lockout, at) else {
+                panic!("a wrong PIN past the free attempts did not lock the prompt");
+            };
+            lockouts.push(duration.as_secs());
+            at += duration;
+        }
+
+        assert_eq!(lockouts, [30, 60, 120, 240, 480, 900, 900, 900]);
+    }
+
+    #[test]
+    fn correct_pin_resets_the_count() {
+        let mut lockout = PinLockout::default();
+        let mut at = Instant::now();
+        for _ in 0..=FREE_ATTEMPTS {
+            wrong(&mut lockout, at);
+        }
+
+        at += BASE_LOCKOUT;
+        assert_eq!(lockout.attempt(at, || true), Attempt::Accepted);
+        assert_eq!(wrong(&mut lockout, at), Attempt::Wrong);
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use slint::{Timer, TimerMode};
+use time::OffsetDateTime;
+
+use crate::config::{Config, StudentData};
+use crate::spreadsheet::{Id, Report, Student, load_student_info};
+
+/// How often the roster file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+type ReloadCallback = Box<dyn Fn(&Reload)>;
+
+/// The result of the latest attempt to load the roster.
+///
+/// # Variants
+///
+/// * `Loaded` - The roster was replaced with `students` students read from the file. `report`
+///   lists the problems found in the file.
+/// * `Failed` - The file could not be read, so the previous roster was kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reload {
+    Loaded {
+        at: OffsetDateTime,
+        students: usize,
+        report: Report,
+    },
+    Failed {
+        at: OffsetDateTime,
+        error: String,
+    },
+}
+
+/// What the roster was loaded from. A change to any of it means the roster has to be reloaded.
+///
+/// # Fields
+///
+/// * `student_data` - The settings the file was read with.
+/// * `modified` - When the file was last modified, or `None` if it could not be read.
+/// * `len` - The size of the file in bytes, or `None` if it could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Source {
+    student_data: StudentData,
+    modified: Option<SystemTime>,
+    len: Option<u64>,
+}
+
+impl Source {
+    fn of(config: &Config) -> Self {
+        let metadata = fs::metadata(&config.student_data.filepath).ok();
+
+        Self {
+            student_data: config.student_data.clone(),
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            len: metadata.as_ref().map(fs::Metadata::len),
+        }
+    }
+}
+
+/// Keeps the roster in sync with the student data file.
+///
+/// The file and the student data settings are polled every `POLL_INTERVAL`. A change is only
+/// picked up once it has been stable for a whole interval, so that a file that is still being
+/// saved is not read. If the new file cannot be read, the previous roster is kept.
+///
+/// # Fields
+///
+/// * `config` - The configuration holding the student data settings.
+/// * `roster` - The roster shared with the rest of the application, replaced on every reload.
+/// * `loaded` - What the current roster was loaded from.
+/// * `changed` - The change seen in the previous poll, if it has not been loaded yet.
+/// * `last_reload` - The result of the latest reload.
+/// * `on_reload` - Called after every reload.
+/// * `timer` - The timer driving the polling.
+pub struct RosterWatcher {
+    config: Rc<RefCell<Config>>,
+    roster: Rc<RefCell<HashMap<Id, Student>>>,
+    loaded: RefCell<Option<Source>>,
+    changed: RefCell<Option<Source>>,
+    last_reload: RefCell<Option<Reload>>,
+    on_reload: RefCell<Option<ReloadCallback>>,
+    timer: Timer,
+}
+
+impl RosterWatcher {
+    /// Creates a watcher and loads the roster for the first time.
+    ///
+    /// Polling does not begin until `start` is called.
+    pub fn new(config: Rc<RefCell<Config>>, roster: Rc<RefCell<HashMap<Id, Student>>>) -> Rc<Self> {
+        let watcher = Rc::new(Self {
+            config,
+            roster,
+            loaded: RefCell::new(None),
+            changed: RefCell::new(None),
+            last_reload: RefCell::new(None),
+            on_reload: RefCell::new(None),
+            timer: Timer::default(),
+        });
+
+        let source = Source::of(&watcher.config.borrow());
+        watcher.reload(source);
+
+        watcher
+    }
+
+    /// Starts polling for changes.
+    pub fn start(self: &Rc<Self>) {
+        let watcher = Rc::downgrade(self);
+        self.timer
+            .start(TimerMode::Repeated, POLL_INTERVAL, move || {
+                if let Some(watcher) = watcher.upgrade() {
+                    watcher.poll();
+                }
+            });
+    }
+
+    /// Returns the result of the latest reload.
+    pub fn last_reload(&self) -> Option<Reload> {
+        self.last_reload.borrow().clone()
+    }
+
+    /// Sets the function called after every reload.
+    pub fn on_reload(&self, on_reload: impl Fn(&Reload) + 'static) {
+        *self.on_reload.borrow_mut() = Some(Box::new(on_reload));
+    }
+
+    fn poll(&self) {
+        let source = Source::of(&self.config.borrow());
+        if self.loaded.borrow().as_ref() == Some(&source) {
+            self.changed.replace(None);
+            return;
+        }
+
+        // wait for the change to settle before reading the file
+        if self.changed.borrow().as_ref() != Some(&source) {
+            self.changed.replace(Some(source));
+            return;
+        }
+
+        self.changed.replace(None);
+        self.reload(source);
+    }
+
+    fn reload(&self, source: Source) {
+        let at = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let reload = match load_student_info(&self.config.borrow()) {
+            Ok((students, report)) => {
+                let count = students.len();
+                *self.roster.borrow_mut() = students;
+                Reload::Loaded {
+                    at,
+                    students: count,
+                    report,
+                }
+            }
+            Err(e) => {
+                eprintln!("Error when loading student data: {e}");
+                Reload::Failed {
+                    at,
+                    error: e.to_string(),
+                }
+            }
+        };
+
+        self.loaded.replace(Some(source));
+        self.last_reload.replace(Some(reload.clone()));
+        if let Some(on_reload) = self.on_reload.borrow().as_ref() {
+            on_reload(&reload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tempfile::{TempDir, tempdir};
+
+    use super::*;
+    use crate::spreadsheet::Column;
+
+    /// Writes `contents` to a CSV file and returns a watcher that has loaded it.
+    fn watch(contents: &str) -> (TempDir, PathBuf, Rc<RosterWatcher>) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("roster.csv");
+        fs::write(&path, contents).unwrap();
+
+        let mut config = Config::default();
+        config.student_data.filepath = path.clone();
+        config.student_data.id_column = Column::Header("Student ID".to_owned());
+        config.student_data.name_column = Column::Header("Name".to_owned());
+        let watcher = RosterWatcher::new(
+            Rc::new(RefCell::new(config)),
+            Rc::new(RefCell::new(HashMap::new())),
+        );
+        (dir, path, watcher)
+    }
+
+    fn names(watcher: &RosterWatcher) -> Vec<String> {
+        let mut names: Vec<_> = watcher
+            .roster
+            .borrow()
+            .values()
+            .map(|student| student.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn changed_file_is_reloaded() {
+        let (_dir, path, watcher) = watch("Student ID,Name\n00123,Aiko\n");
+        let reloads = Rc::new(RefCell::new(0));
+        watcher.on_reload({
+            let reloads = reloads.clone();
+            move |_| *reloads.borrow_mut() += 1
+        });
+
+        fs::write(&path, "Student ID,Name\n00123,Aiko\n00124,Ben\n").unwrap();
+        watcher.poll();
+        watcher.poll();
+
+        assert_eq!(names(&watcher), ["Aiko", "Ben"]);
+        assert_eq!(*reloads.borrow(), 1);
+        assert!(matches!(
+            watcher.last_reload(),
+            Some(Reload::Loaded { students: 2, .. })
+        ));
+
+        // nothing changed since, so there is nothing to reload
+        watcher.poll();
+        watcher.poll();
+        assert_eq!(*reloads.borrow(), 1);
+    }
+
+    #[test]
+    fn failed_reload_keeps_the_previous_roster() {
+        let (_dir, path, watcher) = watch("Student ID,Name\n00123,Aiko\n");
+        let reported = Rc::new(RefCell::new(None));
+        watcher.on_reload({
+            let reported = reported.clone();
+            move |reload| *reported.borrow_mut() = Some(reload.clone())
+        });
+
+        fs::write(&path, "ID,Name\n00123,Aiko\n00124,Ben\n").unwrap();
+        watcher.poll();
+        watcher.poll();
+
+        assert_eq!(names(&watcher), ["Aiko"]);
+        let Some(Reload::Failed { error, .. }) = watcher.last_reload() else {
+            panic!("the reload did not fail");
+        };
+        assert!(error.contains("Student ID"));
+        assert_eq!(*reported.borrow(), watcher.last_reload());
+    }
+
+    #[test]
+    fn file_is_not_loaded_until_it_stops_changing() {
+        let (_dir, path, watcher) = watch("Student ID,Name\n00123,Aiko\n");
+
+        fs::write(&path, "Student ID,Name\n00124,Ben\n").unwrap();
+        watcher.poll();
+        assert_eq!(names(&watcher), ["Aiko"]);
+
+        // the file is still being written
+        fs::write(&path, "Student ID,Name\n00124,Ben\n00125,Chiyo\n").unwrap();
+        watcher.poll();
+        assert_eq!(names(&watcher), ["Aiko"]);
+
+        watcher.poll();
+        assert_eq!(names(&watcher), ["Ben", "Chiyo"]);
+    }
+}
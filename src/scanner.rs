@@ -0,0 +1,200 @@
+use std::time::{Duration, Instant};
+
+use crate::config::Scanner;
+
+/// The longest pause between two keys of a scan. People do not type this fast, so slower input is
+/// taken to be typed by hand.
+const MAX_KEY_GAP: Duration = Duration::from_millis(50);
+/// The fewest characters a scan can have, so that a couple of quick key presses are not taken for
+/// a scan.
+const MIN_LENGTH: usize = 3;
+/// The text of the key that ends a scan.
+const ENTER: &str = "\n";
+
+/// Recognises codes read by a barcode or QR scanner that acts as a keyboard.
+///
+/// Such a scanner types the code much faster than a person could and finishes it with Enter, so
+/// every key pressed on the home page is fed to the detector and a burst of fast keys ending in
+/// Enter is taken as a scan.
+///
+/// # Fields
+///
+/// * `buffer` - The keys of the current burst.
+/// * `last_key` - When the last key of the burst was pressed.
+#[derive(Debug, Default)]
+pub struct ScanDetector {
+    buffer: String,
+    last_key: Option<Instant>,
+}
+
+impl ScanDetector {
+    /// Feeds a key press to the detector.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text of the key, as reported by the ui.
+    /// * `at` - When the key was pressed.
+    /// * `scanner` - The scanner settings, used to strip the prefix and suffix of the code.
+    ///
+    /// # Returns
+    ///
+    /// Returns the scanned code if the key was the Enter that ends a scan, or `None` otherwise.
+    pub fn key(&mut self, text: &str, at: Instant, scanner: &Scanner) -> Option<String> {
+        let fast = self
+            .last_key
+            .is_some_and(|last_key| at.duration_since(last_key) <= MAX_KEY_GAP);
+        if !fast {
+            self.buffer.clear();
+        }
+        self.last_key = Some(at);
+
+        if text == ENTER {
+            let scanned = std::mem::take(&mut self.buffer);
+            self.last_key = None;
+            if !fast || scanned.chars().count() < MIN_LENGTH {
+                return None;
+            }
+
+            // the settings are filtered like the keys, so a suffix such as "\t" cannot stop the
+            // rest of it from matching
+            let prefix = typed(&scanner.prefix);
+            let suffix = typed(&scanner.suffix);
+            let code = scanned.strip_prefix(&prefix).unwrap_or(&scanned);
+            let code = code.strip_suffix(&suffix).unwrap_or(code);
+            return Some(code.to_owned()).filter(|code| !code.is_empty());
+        }
+
+        self.buffer.push_str(&typed(text));
+
+        None
+    }
+}
+
+/// Removes the characters of `text` that are not typed into a scan.
+///
+/// Modifiers and other special keys are reported as control or private use characters, and the
+/// Enter that ends a scan is a control character too.
+fn typed(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() && !('\u{E000}'..='\u{F8FF}').contains(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `keys` to `detector` one character at a time, `gap` apart from `start`, and then
+    /// presses Enter.
+    fn scan_from(
+        detector: &mut ScanDetector,
+        start: Instant,
+        keys: &str,
+        gap: Duration,
+        scanner: &Scanner,
+    ) -> Option<String> {
+        let mut at = start;
+        for key in keys.chars() {
+            assert_eq!(detector.key(&key.to_string(), at, scanner), None);
+            at += gap;
+        }
+        detector.key(ENTER, at, scanner)
+    }
+
+    /// Like `scan_from`, starting now.
+    fn scan(
+        detector: &mut ScanDetector,
+        keys: &str,
+        gap: Duration,
+        scanner: &Scanner,
+    ) -> Option<String> {
+        scan_from(detector, Instant::now(), keys, gap, scanner)
+    }
+
+    #[test]
+    fn fast_keys_ending_in_enter_are_a_scan() {
+        let mut detector = ScanDetector::default();
+
+        let code = scan(&mut detector, "00123", MAX_KEY_GAP, &Scanner::default());
+
+        assert_eq!(code.as_deref(), Some("00123"));
+    }
+
+    #[test]
+    fn slow_keys_are_typed_by_hand() {
+        let mut detector = ScanDetector::default();
+        let gap = MAX_KEY_GAP + Duration::from_millis(1);
+
+        assert_eq!(scan(&mut detector, "00123", gap, &Scanner::default()), None);
+    }
+
+    #[test]
+    fn a_pause_starts_a_new_scan() {
+        let mut detector = ScanDetector::default();
+        let scanner = Scanner::default();
+        let start = Instant::now();
+
+        detector.key("9", start, &scanner);
+        detector.key("9", start + Duration::from_millis(10), &scanner);
+        let resumed = start + Duration::from_millis(10) + MAX_KEY_GAP + Duration::from_millis(1);
+        let code = scan_from(
+            &mut detector,
+            resumed,
+            "123",
+            Duration::from_millis(10),
+            &scanner,
+        );
+
+        assert_eq!(code.as_deref(), Some("123"));
+    }
+
+    #[test]
+    fn scans_shorter_than_the_minimum_are_ignored() {
+        let mut detector = ScanDetector::default();
+        let short = "1".repeat(MIN_LENGTH - 1);
+
+        assert_eq!(
+            scan(&mut detector, &short, Duration::ZERO, &Scanner::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn prefix_and_suffix_are_removed() {
+        let mut detector = ScanDetector::default();
+        let scanner = Scanner {
+            prefix: "ID:".to_owned(),
+            suffix: "#".to_owned(),
+            ..Scanner::default()
+        };
+
+        let code = scan(&mut detector, "ID:00123#", Duration::ZERO, &scanner);
+
+        assert_eq!(code.as_deref(), Some("00123"));
+    }
+
+    #[test]
+    fn control_characters_in_the_settings_are_ignored() {
+        let mut detector = ScanDetector::default();
+        let scanner = Scanner {
+            prefix: "\u{2}".to_owned(),
+            suffix: "#\n".to_owned(),
+            ..Scanner::default()
+        };
+
+        let code = scan(&mut detector, "\u{2}00123#", Duration::ZERO, &scanner);
+
+        assert_eq!(code.as_deref(), Some("00123"));
+    }
+
+    #[test]
+    fn a_code_of_only_prefix_and_suffix_is_not_a_scan() {
+        let mut detector = ScanDetector::default();
+        let scanner = Scanner {
+            prefix: "ID:".to_owned(),
+            ..Scanner::default()
+        };
+
+        assert_eq!(scan(&mut detector, "ID:", Duration::ZERO, &scanner), None);
+    }
+}
@@ -17,14 +17,45 @@ use tokio::{
     time::sleep,
 };
 
+type BoxedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
 struct Task {
     pub at: OffsetDateTime,
-    pub future: Pin<Box<dyn Future<Output = ()> + Send>>,
+    pub future: BoxedFuture,
+    /// If `Some`, the task is scheduled again after it runs.
+    pub repeat: Option<Repeat>,
+}
+
+struct Repeat {
+    pub interval: Duration,
+    pub make_future: Box<dyn FnMut() -> BoxedFuture + Send>,
+}
+
+impl Repeat {
+    /// Creates the occurrence that follows one scheduled for `previous`, skipping any
+    /// occurrences that were missed entirely.
+    fn next_task(mut self, previous: OffsetDateTime, now: OffsetDateTime) -> Task {
+        let mut at = previous + self.interval;
+        while at <= now {
+            at += self.interval;
+        }
+        Task {
+            at,
+            future: (self.make_future)(),
+            repeat: Some(self),
+        }
+    }
 }
 
 impl fmt::Debug for Task {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Task").field("at", &self.at).finish()
+        f.debug_struct("Task")
+            .field("at", &self.at)
+            .field(
+                "interval",
+                &self.repeat.as_ref().map(|repeat| repeat.interval),
+            )
+            .finish()
     }
 }
 
@@ -44,7 +75,8 @@ impl PartialOrd for Task {
 
 impl Ord for Task {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.at.cmp(&other.at)
+        // reversed so that the `BinaryHeap` (a max-heap) yields the earliest task first
+        other.at.cmp(&self.at)
     }
 }
 
@@ -101,14 +133,51 @@ impl Scheduler {
             return Ok(());
         }
 
+        self.add(Task {
+            at,
+            future: Box::pin(future),
+            repeat: None,
+        })
+    }
+
+    /// Schedules a task that first runs at `at` and then every `interval` after that.
+    ///
+    /// `make_future` is called to create a fresh future for each run. If the runner falls behind
+    /// by more than one interval, the missed runs are skipped rather than run back to back.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ScheduleError::ZeroInterval` if `interval` is zero, or
+    /// `ScheduleError::TaskRunnerFailedToStart` if the task runner is not executing.
+    pub fn schedule_repeating<F, Fut>(
+        &mut self,
+        at: OffsetDateTime,
+        interval: Duration,
+        mut make_future: F,
+    ) -> Result<(), ScheduleError>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if interval.is_zero() {
+            return Err(ScheduleError::ZeroInterval);
+        }
+
+        self.add(Task {
+            at,
+            future: Box::pin(make_future()),
+            repeat: Some(Repeat {
+                interval,
+                make_future: Box::new(move || Box::pin(make_future())),
+            }),
+        })
+    }
+
+    fn add(&mut self, task: Task) -> Result<(), ScheduleError> {
         if self.tx.is_none() {
             self.launch_task_thread();
         }
         if let Some(tx) = &self.tx {
-            let task = Task {
-                at,
-                future: Box::pin(future),
-            };
             let _ = tx.send(Request::Add(task));
             Ok(())
         } else {
@@ -126,23 +195,26 @@ impl Scheduler {
 
             loop {
                 let now = now_local_or_utc();
-                while let Some(task) = heap.pop_if(|task| task.at <= now) {
+                // called through the trait, since `BinaryHeap` is gaining an unstable `pop_if`
+                while let Some(task) = PopIf::pop_if(&mut heap, |task| task.at <= now) {
+                    let Task { at, future, repeat } = task;
                     // Should never fail since the loop only runs if the task is in the past.
                     // If it is negative, indicating that the task is in the future, we can skip the task.
-                    let late_by = (now - task.at).try_into().unwrap_or(Duration::MAX);
+                    let late_by = (now - at).try_into().unwrap_or(Duration::MAX);
+                    if let Some(repeat) = repeat {
+                        heap.push(repeat.next_task(at, now));
+                    }
                     if let Some(limit) = config.catch_up_limit
                         && late_by > limit
                     {
                         eprintln!(
                             "skipping stale task scheduled for {} (late by {}s)",
-                            task.at,
+                            at,
                             late_by.as_secs()
                         );
                         continue;
                     }
-                    spawn(async move {
-                        task.future.await;
-                    });
+                    spawn(future);
                 }
 
                 let sleep_time = heap
@@ -205,3 +277,104 @@ impl<T: Ord> PopIf<T> for BinaryHeap<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering as AtomicOrdering},
+    };
+
+    use tokio::runtime::Builder;
+
+    use super::*;
+
+    fn task(at: OffsetDateTime) -> Task {
+        Task {
+            at,
+            future: Box::pin(async {}),
+            repeat: None,
+        }
+    }
+
+    /// Returns a repeat that counts how many futures it has made.
+    fn counting_repeat(interval: Duration) -> (Repeat, Arc<AtomicUsize>) {
+        let made = Arc::new(AtomicUsize::new(0));
+        let repeat = Repeat {
+            interval,
+            make_future: Box::new({
+                let made = made.clone();
+                move || {
+                    made.fetch_add(1, AtomicOrdering::SeqCst);
+                    Box::pin(async {})
+                }
+            }),
+        };
+        (repeat, made)
+    }
+
+    #[test]
+    fn heap_yields_the_earliest_task_first() {
+        let start = OffsetDateTime::UNIX_EPOCH;
+        let mut heap = BinaryHeap::new();
+        for minutes in [5, 1, 3, 0, 4] {
+            heap.push(task(start + Duration::from_secs(minutes * 60)));
+        }
+
+        let order: Vec<_> = std::iter::from_fn(|| heap.pop())
+            .map(|task| (task.at - start).whole_minutes())
+            .collect();
+
+        assert_eq!(order, [0, 1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn only_due_tasks_are_popped() {
+        let start = OffsetDateTime::UNIX_EPOCH;
+        let mut heap = BinaryHeap::from([task(start + Duration::from_secs(60)), task(start)]);
+
+        assert!(PopIf::pop_if(&mut heap, |task| task.at <= start).is_some());
+        assert!(PopIf::pop_if(&mut heap, |task| task.at <= start).is_none());
+        assert_eq!(heap.len(), 1);
+    }
+
+    #[test]
+    fn repeat_is_scheduled_one_interval_later() {
+        let start = OffsetDateTime::UNIX_EPOCH;
+        let interval = Duration::from_secs(60);
+        let (repeat, made) = counting_repeat(interval);
+
+        let next = repeat.next_task(start, start + Duration::from_secs(1));
+
+        assert_eq!(next.at, start + interval);
+        assert_eq!(made.load(AtomicOrdering::SeqCst), 1);
+
+        let next = next.repeat.unwrap().next_task(next.at, next.at);
+
+        assert_eq!(next.at, start + interval * 2);
+        assert_eq!(made.load(AtomicOrdering::SeqCst), 2);
+    }
+
+    #[test]
+    fn missed_repeats_are_skipped() {
+        let start = OffsetDateTime::UNIX_EPOCH;
+        let interval = Duration::from_secs(60);
+        let (repeat, made) = counting_repeat(interval);
+
+        let next = repeat.next_task(start, start + interval * 3);
+
+        assert_eq!(next.at, start + interval * 4);
+        assert_eq!(made.load(AtomicOrdering::SeqCst), 1);
+    }
+
+    #[test]
+    fn zero_interval_is_rejected() {
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        let mut scheduler = Scheduler::new(runtime, Config::default());
+
+        let result = scheduler.schedule_repeating(now_local_or_utc(), Duration::ZERO, || async {});
+
+        assert!(matches!(result, Err(ScheduleError::ZeroInterval)));
+        assert!(scheduler.tx.is_none());
+    }
+}
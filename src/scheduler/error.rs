@@ -5,4 +5,7 @@ pub enum ScheduleError {
     /// The task runner is not executing. This also implies that the task has been discarded.
     #[error("the task runner does not seem to be able to start")]
     TaskRunnerFailedToStart,
+    /// A repeating task was given an interval of zero, so it could never be scheduled again.
+    #[error("a repeating task cannot have an interval of zero")]
+    ZeroInterval,
 }
@@ -0,0 +1,229 @@
+mod error;
+mod file;
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+mod secret_service;
+
+use std::{cell::RefCell, collections::HashMap, fmt::Debug, path::Path, rc::Rc};
+
+pub use error::Error;
+use error::Result;
+pub use file::{EncryptedFile, PASSPHRASE_VAR};
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+pub use secret_service::SecretService;
+
+use crate::config::SecretBackend;
+
+/// The key the MyStudio account email is stored under.
+pub const MY_STUDIO_EMAIL: &str = "my_studio.email";
+
+/// A place to keep credentials out of the plaintext configuration file.
+pub trait SecretStore: Debug {
+    /// Returns a name for the store that can be shown to the user.
+    fn name(&self) -> &'static str;
+
+    /// Returns the secret stored under `key`, or `None` if there is none.
+    fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Stores `value` under `key`, replacing any previous secret.
+    fn set(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Removes the secret stored under `key`, if there is one.
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Opens the secret store chosen by `backend`.
+///
+/// # Arguments
+///
+/// * `backend` - The configured backend.
+/// * `dir` - The directory the file backend keeps its files in.
+///
+/// # Errors
+///
+/// Returns an error if the chosen backend cannot be opened. `SecretBackend::Auto` only fails if
+/// the secrets file cannot be opened.
+pub fn open(backend: &SecretBackend, dir: &Path) -> Result<Rc<dyn SecretStore>> {
+    match backend {
+        SecretBackend::Auto => {
+            #[cfg(all(target_os = "linux", feature = "secret-service"))]
+            match SecretService::connect() {
+                Ok(store) => return Ok(Rc::new(store)),
+                Err(e) => {
+                    eprintln!("The Secret Service is not available, using a secrets file: {e}")
+                }
+            }
+            Ok(Rc::new(EncryptedFile::open(dir)?))
+        }
+        #[cfg(all(target_os = "linux", feature = "secret-service"))]
+        SecretBackend::SecretService => Ok(Rc::new(SecretService::connect()?)),
+        #[cfg(not(all(target_os = "linux", feature = "secret-service")))]
+        SecretBackend::SecretService => Err(Error::Unsupported {
+            backend: "SecretService".to_owned(),
+        }),
+        SecretBackend::File => Ok(Rc::new(EncryptedFile::open(dir)?)),
+    }
+}
+
+/// A shared handle to the secret store used by a configuration.
+///
+/// Writes of a secret that has not changed since it was last read or written are skipped, so that
+/// saving the configuration often does not keep rewriting the store.
+///
+/// # Fields
+///
+/// * `store` - The secret store, or `None` if secrets are not stored anywhere.
+/// * `known` - The last value read from or written to the store, by key.
+#[derive(Debug, Clone, Default)]
+pub struct SecretHandle {
+    store: Option<Rc<dyn SecretStore>>,
+    known: Rc<RefCell<HashMap<String, String>>>,
+}
+
+impl PartialEq for SecretHandle {
+    /// Handles are not part of the configuration's value, so they are all equal.
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SecretHandle {}
+
+impl SecretHandle {
+    pub fn new(store: Rc<dyn SecretStore>) -> Self {
+        Self {
+            store: Some(store),
+            known: Rc::default(),
+        }
+    }
+
+    /// Returns the secret store, if there is one.
+    pub fn store(&self) -> Option<&dyn SecretStore> {
+        self.store.as_deref()
+    }
+
+    /// Reads a secret. An empty string is returned if there is no store or no secret.
+    pub fn get(&self, key: &str) -> Result<String> {
+        let Some(store) = &self.store else {
+            return Ok(String::new());
+        };
+
+        let value = store.get(key)?.unwrap_or_default();
+        self.known
+            .borrow_mut()
+            .insert(key.to_owned(), value.clone());
+        Ok(value)
+    }
+
+    /// Writes a secret if it changed. An empty value removes the secret.
+    pub fn set(&self, key: &str, value: &str) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        if self.known.borrow().get(key).map(String::as_str) == Some(value) {
+            return Ok(());
+        }
+
+        if value.is_empty() {
+            store.delete(key)?;
+        } else {
+            store.set(key, value)?;
+        }
+        self.known
+            .borrow_mut()
+            .insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store that keeps secrets in memory and counts the writes it receives.
+    #[derive(Debug, Default)]
+    struct MemoryStore {
+        secrets: RefCell<HashMap<String, String>>,
+        writes: RefCell<u32>,
+    }
+
+    impl SecretStore for MemoryStore {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn get(&self, key: &str) -> Result<Option<String>> {
+            Ok(self.secrets.borrow().get(key).cloned())
+        }
+
+        fn set(&self, key: &str, value: &str) -> Result<()> {
+            *self.writes.borrow_mut() += 1;
+            self.secrets
+                .borrow_mut()
+                .insert(key.to_owned(), value.to_owned());
+            Ok(())
+        }
+
+        fn delete(&self, key: &str) -> Result<()> {
+            *self.writes.borrow_mut() += 1;
+            self.secrets.borrow_mut().remove(key);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn unchanged_secret_is_not_written_again() {
+        let store = Rc::new(MemoryStore::default());
+        let handle = SecretHandle::new(store.clone());
+
+        handle
+            .set(MY_STUDIO_EMAIL, "frontdesk@example.com")
+            .unwrap();
+        handle
+            .set(MY_STUDIO_EMAIL, "frontdesk@example.com")
+            .unwrap();
+        handle.set(MY_STUDIO_EMAIL, "office@example.com").unwrap();
+
+        assert_eq!(*store.writes.borrow(), 2);
+        assert_eq!(handle.get(MY_STUDIO_EMAIL).unwrap(), "office@example.com");
+    }
+
+    #[test]
+    fn secret_read_from_the_store_is_not_written_back() {
+        let store = Rc::new(MemoryStore::default());
+        store.secrets.borrow_mut().insert(
+            MY_STUDIO_EMAIL.to_owned(),
+            "frontdesk@example.com".to_owned(),
+        );
+        let handle = SecretHandle::new(store.clone());
+
+        let email = handle.get(MY_STUDIO_EMAIL).unwrap();
+        handle.set(MY_STUDIO_EMAIL, &email).unwrap();
+
+        assert_eq!(*store.writes.borrow(), 0);
+    }
+
+    #[test]
+    fn empty_secret_is_deleted() {
+        let store = Rc::new(MemoryStore::default());
+        let handle = SecretHandle::new(store.clone());
+
+        handle
+            .set(MY_STUDIO_EMAIL, "frontdesk@example.com")
+            .unwrap();
+        handle.set(MY_STUDIO_EMAIL, "").unwrap();
+
+        assert!(store.secrets.borrow().is_empty());
+    }
+
+    #[test]
+    fn handle_without_a_store_keeps_nothing() {
+        let handle = SecretHandle::default();
+
+        handle
+            .set(MY_STUDIO_EMAIL, "frontdesk@example.com")
+            .unwrap();
+
+        assert!(handle.store().is_none());
+        assert_eq!(handle.get(MY_STUDIO_EMAIL).unwrap(), "");
+    }
+}
@@ -0,0 +1,53 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Represents errors that can occur in the `secrets` module.
+///
+/// # Variants
+///
+/// * `Io` - Represents I/O errors when reading or writing the encrypted secrets file.
+/// * `Json` - Represents errors when serializing or deserializing the stored secrets.
+/// * `KeyDerivation` - Represents errors when deriving the encryption key from the passphrase.
+/// * `Encryption` - Represents errors when encrypting the secrets file.
+/// * `WrongPassphrase` - Indicates that the secrets file cannot be decrypted with the passphrase.
+/// * `Corrupt` - Indicates that the secrets file is too short or not a secrets file at all.
+/// * `SecretService` - Represents errors reported by the Linux Secret Service.
+/// * `Unsupported` - Indicates that the configured backend is not available in this build.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Represents I/O errors that occur during file operations.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// A JSON serialization error, originating from the `serde_json` library.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// Represents errors when deriving the encryption key, originating from the `argon2` library.
+    #[error("Cannot derive the encryption key: {0}")]
+    KeyDerivation(argon2::Error),
+
+    /// Represents errors when encrypting the secrets, originating from the `chacha20poly1305`
+    /// library.
+    #[error("Cannot encrypt the secrets: {0}")]
+    Encryption(chacha20poly1305::Error),
+
+    /// Indicates that the secrets file was encrypted with another passphrase, or was tampered with.
+    #[error("Cannot decrypt '{path}', the passphrase is wrong or the file is damaged.")]
+    WrongPassphrase { path: String },
+
+    /// Indicates that the secrets file does not have the expected layout.
+    #[error("'{path}' is not a secrets file.")]
+    Corrupt { path: String },
+
+    /// Represents errors reported by the Linux Secret Service, such as when no keyring is running.
+    #[cfg(all(target_os = "linux", feature = "secret-service"))]
+    #[error("Secret Service error: {0}")]
+    SecretService(#[from] secret_service::Error),
+
+    /// Indicates that the configured backend was not compiled in or does not exist on this platform.
+    #[cfg(not(all(target_os = "linux", feature = "secret-service")))]
+    #[error("The '{backend}' secret store is not available in this build.")]
+    Unsupported { backend: String },
+}
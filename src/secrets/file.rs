@@ -0,0 +1,366 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    env, fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    AeadCore, ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, OsRng, rand_core::RngCore},
+};
+
+use super::SecretStore;
+use super::error::{Error, Result};
+use crate::config;
+
+/// The name of the secrets file, which is stored next to the configuration file.
+pub const FILE_NAME: &str = "secrets.enc";
+/// The name of the file holding the generated passphrase, used when `PASSPHRASE_VAR` is not set.
+pub const PASSPHRASE_FILE_NAME: &str = "secrets.key";
+/// The environment variable the passphrase can be given in instead of a passphrase file.
+pub const PASSPHRASE_VAR: &str = "YOUKOSO_SECRETS_PASSPHRASE";
+
+/// Marks the start of a secrets file, and its format version.
+const MAGIC: &[u8; 4] = b"YKS1";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Secrets kept in a file encrypted with ChaCha20-Poly1305.
+///
+/// The key is derived from a local passphrase with Argon2. The passphrase is read from the
+/// `PASSPHRASE_VAR` environment variable or, if that is not set, from a passphrase file that is
+/// generated the first time it is needed. This works without a desktop session, so it is used on
+/// headless kiosks.
+///
+/// Only a passphrase from `PASSPHRASE_VAR` protects the secrets. The passphrase file is kept next
+/// to the secrets file, so anyone who can copy the directory can decrypt them, and on platforms
+/// other than unix the passphrase file is not even limited to the current user. Without the
+/// variable, the secrets are only obfuscated, which keeps them out of the configuration file and
+/// from being read at a glance.
+///
+/// The file is laid out as `MAGIC`, the salt, the nonce and then the encrypted JSON object of
+/// secrets. A new nonce is used every time the file is written.
+///
+/// # Fields
+///
+/// * `path` - The path of the secrets file.
+/// * `salt` - The salt the key was derived with.
+/// * `cipher` - The cipher keyed with the derived key.
+/// * `secrets` - The decrypted secrets, by key.
+/// * `obfuscated` - Whether the passphrase was read from the passphrase file, so the secrets are
+///   only obfuscated.
+pub struct EncryptedFile {
+    path: PathBuf,
+    salt: [u8; SALT_LENGTH],
+    cipher: ChaCha20Poly1305,
+    secrets: RefCell<BTreeMap<String, String>>,
+    obfuscated: bool,
+}
+
+impl fmt::Debug for EncryptedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the secrets and the key are left out so that they do not end up in logs
+        f.debug_struct("EncryptedFile")
+            .field("path", &self.path)
+            .field("obfuscated", &self.obfuscated)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedFile {
+    /// Opens the secrets file in `dir`, creating an empty store if it does not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the passphrase cannot be read or generated, or if the file exists but
+    /// cannot be read or decrypted.
+    pub fn open(dir: &Path) -> Result<Self> {
+        Self::open_with(dir, env::var(PASSPHRASE_VAR).ok())
+    }
+
+    /// Opens the secrets file in `dir` like `open` does, with `configured` in place of the value
+    /// of `PASSPHRASE_VAR`.
+    fn open_with(dir: &Path, configured: Option<String>) -> Result<Self> {
+        let path = dir.join(FILE_NAME);
+        let (passphrase, obfuscated) = passphrase(dir, configured)?;
+
+        if !path.exists() {
+            let mut salt = [0; SALT_LENGTH];
+            OsRng.fill_bytes(&mut salt);
+            return Ok(Self {
+                cipher: cipher(&passphrase, &salt)?,
+                path,
+                salt,
+                secrets: RefCell::new(BTreeMap::new()),
+                obfuscated,
+            });
+        }
+
+        let contents = fs::read(&path)?;
+        let corrupt = || Error::Corrupt {
+            path: path.display().to_string(),
+        };
+        let contents = contents.strip_prefix(MAGIC).ok_or_else(corrupt)?;
+        if contents.len() < SALT_LENGTH + NONCE_LENGTH {
+            return Err(corrupt());
+        }
+        let (salt, contents) = contents.split_at(SALT_LENGTH);
+        let (nonce, ciphertext) = contents.split_at(NONCE_LENGTH);
+        let salt: [u8; SALT_LENGTH] = salt.try_into().unwrap();
+
+        let cipher = cipher(&passphrase, &salt)?;
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::WrongPassphrase {
+                path: path.display().to_string(),
+            })?;
+
+        Ok(Self {
+            secrets: RefCell::new(serde_json::from_slice(&plaintext)?),
+            path,
+            salt,
+            cipher,
+            obfuscated,
+        })
+    }
+
+    /// Encrypts the secrets and replaces the file with them.
+    fn write(&self) -> Result<()> {
+        let plaintext = serde_json::to_vec(&*self.secrets.borrow())?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(Error::Encryption)?;
+
+        let mut contents = Vec::with_capacity(MAGIC.len() + SALT_LENGTH + NONCE_LENGTH);
+        contents.extend_from_slice(MAGIC);
+        contents.extend_from_slice(&self.salt);
+        contents.extend_from_slice(&nonce);
+        contents.extend_from_slice(&ciphertext);
+        // a partly written file could not be decrypted, which would lose every secret
+        config::write_atomically(&self.path, contents)?;
+
+        Ok(())
+    }
+}
+
+impl SecretStore for EncryptedFile {
+    fn name(&self) -> &'static str {
+        if self.obfuscated {
+            "obfuscated file"
+        } else {
+            "encrypted file"
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.secrets.borrow().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.secrets
+            .borrow_mut()
+            .insert(key.to_owned(), value.to_owned());
+        self.write()
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        if self.secrets.borrow_mut().remove(key).is_some() {
+            self.write()?;
+        }
+        Ok(())
+    }
+}
+
+/// Derives the key from the passphrase and returns a cipher using it.
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(Error::KeyDerivation)?;
+    Ok(ChaCha20Poly1305::new(&key))
+}
+
+/// Returns the configured passphrase, or reads it from the passphrase file in `dir`.
+///
+/// If neither exists, a random passphrase is generated and written to the passphrase file. On unix,
+/// only the current user can read it.
+///
+/// # Arguments
+///
+/// * `dir` - The directory the passphrase file is kept in.
+/// * `configured` - The value of `PASSPHRASE_VAR`, if it is set. An empty value is ignored.
+///
+/// # Returns
+///
+/// Returns the passphrase, and whether it came from the passphrase file.
+fn passphrase(dir: &Path, configured: Option<String>) -> Result<(String, bool)> {
+    if let Some(passphrase) = configured
+        && !passphrase.is_empty()
+    {
+        return Ok((passphrase, false));
+    }
+
+    let path = dir.join(PASSPHRASE_FILE_NAME);
+    if path.exists() {
+        return Ok((fs::read_to_string(&path)?.trim().to_owned(), true));
+    }
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let passphrase: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&path)?.write_all(passphrase.as_bytes())?;
+
+    Ok((passphrase, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    fn open(dir: &Path, passphrase: &str) -> Result<EncryptedFile> {
+        EncryptedFile::open_with(dir, Some(passphrase.to_owned()))
+    }
+
+    #[test]
+    fn secret_can_be_read_after_reopening() {
+        let dir = tempdir().unwrap();
+        open(dir.path(), "passphrase")
+            .unwrap()
+            .set("my_studio.password", PASSWORD)
+            .unwrap();
+
+        let reopened = open(dir.path(), "passphrase").unwrap();
+
+        assert_eq!(
+            reopened.get("my_studio.password").unwrap().as_deref(),
+            Some(PASSWORD)
+        );
+        assert_eq!(reopened.name(), "encrypted file");
+        // the secret is not written in plain text
+        let contents = fs::read(dir.path().join(FILE_NAME)).unwrap();
+        assert!(
+            !contents
+                .windows(PASSWORD.len())
+                .any(|window| window == PASSWORD.as_bytes())
+        );
+    }
+
+    #[test]
+    fn deleted_secret_is_gone_after_reopening() {
+        let dir = tempdir().unwrap();
+        let store = open(dir.path(), "passphrase").unwrap();
+        store.set("my_studio.password", PASSWORD).unwrap();
+        store.delete("my_studio.password").unwrap();
+
+        let reopened = open(dir.path(), "passphrase").unwrap();
+
+        assert_eq!(reopened.get("my_studio.password").unwrap(), None);
+    }
+
+    #[test]
+    fn other_passphrase_is_rejected() {
+        let dir = tempdir().unwrap();
+        open(dir.path(), "passphrase")
+            .unwrap()
+            .set("my_studio.password", PASSWORD)
+            .unwrap();
+
+        let result = open(dir.path(), "another passphrase");
+
+        assert!(matches!(result, Err(Error::WrongPassphrase { .. })));
+    }
+
+    #[test]
+    fn damaged_file_is_rejected() {
+        let dir = tempdir().unwrap();
+        open(dir.path(), "passphrase")
+            .unwrap()
+            .set("my_studio.password", PASSWORD)
+            .unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let mut contents = fs::read(&path).unwrap();
+        let last = contents.len() - 1;
+        contents[last] ^= 1;
+        fs::write(&path, contents).unwrap();
+
+        let result = open(dir.path(), "passphrase");
+
+        assert!(matches!(result, Err(Error::WrongPassphrase { .. })));
+    }
+
+    #[test]
+    fn truncated_or_foreign_file_is_corrupt() {
+        let dir = tempdir().unwrap();
+        open(dir.path(), "passphrase")
+            .unwrap()
+            .set("my_studio.password", PASSWORD)
+            .unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let contents = fs::read(&path).unwrap();
+
+        for damaged in [
+            &contents[..MAGIC.len() + SALT_LENGTH],
+            &contents[..2],
+            b"not a secrets file at all".as_slice(),
+        ] {
+            fs::write(&path, damaged).unwrap();
+
+            let result = open(dir.path(), "passphrase");
+
+            assert!(matches!(result, Err(Error::Corrupt { .. })), "{damaged:?}");
+        }
+    }
+
+    #[test]
+    fn passphrase_file_is_created_once_and_reused() {
+        let dir = tempdir().unwrap();
+        let passphrase_path = dir.path().join(PASSPHRASE_FILE_NAME);
+
+        let store = EncryptedFile::open_with(dir.path(), None).unwrap();
+        store.set("my_studio.password", PASSWORD).unwrap();
+        let passphrase = fs::read_to_string(&passphrase_path).unwrap();
+        // an empty variable is treated as not set
+        let reopened = EncryptedFile::open_with(dir.path(), Some(String::new())).unwrap();
+
+        assert_eq!(store.name(), "obfuscated file");
+        assert_eq!(passphrase.len(), 64);
+        assert_eq!(fs::read_to_string(&passphrase_path).unwrap(), passphrase);
+        assert_eq!(
+            reopened.get("my_studio.password").unwrap().as_deref(),
+            Some(PASSWORD)
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&passphrase_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn configured_passphrase_does_not_create_a_passphrase_file() {
+        let dir = tempdir().unwrap();
+
+        open(dir.path(), "passphrase")
+            .unwrap()
+            .set("my_studio.password", PASSWORD)
+            .unwrap();
+
+        assert!(!dir.path().join(PASSPHRASE_FILE_NAME).exists());
+    }
+}
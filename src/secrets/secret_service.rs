@@ -0,0 +1,84 @@
+use std::{collections::HashMap, fmt};
+
+use secret_service::{EncryptionType, blocking::SecretService as Connection};
+
+use super::SecretStore;
+use super::error::Result;
+
+/// The attribute that marks the items stored by this application.
+const APPLICATION_ATTRIBUTE: &str = "application";
+const APPLICATION: &str = "youkoso";
+/// The attribute holding the key of a secret.
+const KEY_ATTRIBUTE: &str = "key";
+
+/// Secrets kept by the Linux Secret Service, such as GNOME Keyring or KWallet.
+///
+/// Each secret is an item in the default collection, found by its `application` and `key`
+/// attributes. This needs a desktop session with a running keyring.
+pub struct SecretService {
+    connection: Connection<'static>,
+}
+
+impl fmt::Debug for SecretService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretService").finish_non_exhaustive()
+    }
+}
+
+impl SecretService {
+    /// Connects to the Secret Service of the current session.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SecretService` if no Secret Service is running.
+    pub fn connect() -> Result<Self> {
+        Ok(Self {
+            connection: Connection::connect(EncryptionType::Dh)?,
+        })
+    }
+
+    fn attributes(key: &str) -> HashMap<&str, &str> {
+        HashMap::from([(APPLICATION_ATTRIBUTE, APPLICATION), (KEY_ATTRIBUTE, key)])
+    }
+}
+
+impl SecretStore for SecretService {
+    fn name(&self) -> &'static str {
+        "Secret Service"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let items = self.connection.search_items(Self::attributes(key))?;
+        let Some(item) = items.unlocked.first().or(items.locked.first()) else {
+            return Ok(None);
+        };
+
+        item.ensure_unlocked()?;
+        Ok(Some(
+            String::from_utf8_lossy(&item.get_secret()?).into_owned(),
+        ))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        let collection = self.connection.get_default_collection()?;
+        collection.ensure_unlocked()?;
+        collection.create_item(
+            &format!("Youkoso {key}"),
+            Self::attributes(key),
+            value.as_bytes(),
+            true,
+            "text/plain",
+        )?;
+
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let items = self.connection.search_items(Self::attributes(key))?;
+        for item in items.unlocked.iter().chain(&items.locked) {
+            item.delete()?;
+        }
+
+        Ok(())
+    }
+}
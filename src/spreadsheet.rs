@@ -1,45 +1,681 @@
 mod error;
 
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    fs,
+};
 
-use calamine::{DataType, Reader, Xlsx, open_workbook};
+use calamine::{Data, Reader, open_workbook_auto};
+use encoding_rs::{Encoding, UTF_8};
+pub use error::Error;
 use error::Result;
+use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
+use crate::config::{Config, IdNormalization, StudentData};
 
 pub type ColumnIndex = u8;
 pub type Id = String;
 
+/// Identifies a column of the roster.
+///
+/// In the configuration file, a number is read as an index, text is read as a header and a table
+/// with both, e.g. `{ header = "Student ID", index = 0 }`, is read as a header with a fallback.
+///
+/// # Variants
+///
+/// * `Index` - The 0-based index of the column.
+/// * `Header` - The text in the column's first row, e.g. `"Student ID"`. Unlike an index, this
+///   keeps working when columns are inserted or moved. Surrounding whitespace and case are
+///   ignored when matching.
+/// * `HeaderOrIndex` - A header, and the index of the column to read if no column has that header,
+///   so that a renamed header does not stop the roster from loading.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Column {
+    Index(ColumnIndex),
+    Header(String),
+    HeaderOrIndex { header: String, index: ColumnIndex },
+}
+
+impl Default for Column {
+    fn default() -> Self {
+        Column::Index(0)
+    }
+}
+
+impl Column {
+    /// Returns the header the column is identified by, if any.
+    pub fn header(&self) -> Option<&str> {
+        match self {
+            Column::Index(_) => None,
+            Column::Header(header) | Column::HeaderOrIndex { header, .. } => Some(header),
+        }
+    }
+
+    /// Returns the index the column is identified by, or falls back to, if any.
+    pub fn index(&self) -> Option<ColumnIndex> {
+        match self {
+            Column::Index(index) | Column::HeaderOrIndex { index, .. } => Some(*index),
+            Column::Header(_) => None,
+        }
+    }
+
+    /// Finds the index of the column.
+    ///
+    /// # Arguments
+    ///
+    /// * `header_row` - The first row of the sheet, if it holds headers.
+    /// * `report` - Records the header if its fallback index is used instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MissingHeader` if the column is identified by a header that is not in
+    /// `header_row` and has no fallback index.
+    fn resolve(&self, header_row: Option<&[Data]>, report: &mut Report) -> Result<usize> {
+        let Some(header) = self.header() else {
+            return Ok(self.index().map_or(0, usize::from));
+        };
+        let found = header_row.and_then(|row| {
+            row.iter().position(|cell| {
+                cell_text(cell).trim().to_lowercase() == header.trim().to_lowercase()
+            })
+        });
+
+        match (found, self.index()) {
+            (Some(found), _) => Ok(found),
+            (None, Some(index)) => {
+                report
+                    .missing_headers
+                    .push((header.to_owned(), Some(index)));
+                Ok(index.into())
+            }
+            (None, None) => Err(Error::MissingHeader {
+                header: header.to_owned(),
+            }),
+        }
+    }
+}
+
+/// A student read from the roster.
+///
+/// # Fields
+///
+/// * `id` - The ID as written in the roster, which is what MyStudio knows the student by. The
+///   roster is keyed by the normalized ID instead, which is only used to look students up.
+/// * `name` - The name of the student.
+/// * `immediate_sign_in` - Whether the student is signed in without being asked to confirm.
+/// * `row` - The 1-based row of the spreadsheet the student was read from.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Student {
+    pub id: Id,
     pub name: String,
     pub immediate_sign_in: bool,
+    pub row: u32,
+}
+
+/// Problems found in the roster while it was loaded.
+///
+/// None of them stop the roster from loading, but they usually mean the spreadsheet needs fixing.
+///
+/// # Fields
+///
+/// * `header_row` - The 1-based row that was read as headers, or `None` if every row was read as
+///   a student.
+/// * `header_row_guessed` - Whether the header row was skipped because it did not look like a
+///   student, rather than because a column is mapped by header.
+/// * `duplicates` - The IDs shared by more than one row, each with the rows it is in. Only the last
+///   of those rows is kept in the roster.
+/// * `empty_ids` - The rows that have data but no ID. These rows are not loaded.
+/// * `missing_names` - The rows that have an ID but no name.
+/// * `missing_headers` - The headers that were not found, each with the 0-based index of the
+///   column that was read instead. The index is `None` for the immediate sign-in column when it
+///   has no fallback, in which case no student signs in immediately.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub header_row: Option<u32>,
+    pub header_row_guessed: bool,
+    pub duplicates: Vec<(Id, Vec<u32>)>,
+    pub empty_ids: Vec<u32>,
+    pub missing_names: Vec<u32>,
+    pub missing_headers: Vec<(String, Option<ColumnIndex>)>,
+}
+
+impl Report {
+    /// Returns whether any problem was found.
+    pub fn has_problems(&self) -> bool {
+        !self.duplicates.is_empty()
+            || !self.empty_ids.is_empty()
+            || !self.missing_names.is_empty()
+            || !self.missing_headers.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    /// Describes the problems found in the roster, one per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows_text = |rows: &[u32]| {
+            let rows: Vec<String> = rows.iter().map(u32::to_string).collect();
+            rows.join(", ")
+        };
+
+        let mut lines = vec![match self.header_row {
+            Some(row) if self.header_row_guessed => {
+                format!("Row {row} has no ID that looks like one, it was read as headers")
+            }
+            Some(row) => format!("Headers read from row {row}"),
+            None => "No header row".to_owned(),
+        }];
+        lines.extend(
+            self.missing_headers
+                .iter()
+                .map(|(header, index)| match index {
+                    Some(index) => {
+                        format!(
+                            "No column has the header '{header}', column {index} was read instead"
+                        )
+                    }
+                    None => format!(
+                        "No column has the header '{header}', no student signs in immediately"
+                    ),
+                }),
+        );
+        lines.extend(
+            self.duplicates
+                .iter()
+                .map(|(id, rows)| format!("ID '{id}' is shared by rows {}", rows_text(rows))),
+        );
+        if !self.empty_ids.is_empty() {
+            lines.push(format!(
+                "No ID in rows {}, they were skipped",
+                rows_text(&self.empty_ids)
+            ));
+        }
+        if !self.missing_names.is_empty() {
+            lines.push(format!(
+                "No name in rows {}",
+                rows_text(&self.missing_names)
+            ));
+        }
+        if !self.has_problems() {
+            lines.push("No problems found".to_owned());
+        }
+
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+/// Loads the roster from the file configured in `config.student_data`.
+///
+/// The file format is chosen by extension: `.csv` and `.tsv` files are read as delimited text,
+/// anything else as a workbook. Workbooks can be in the `.xlsx`, `.xls`, `.xlsb` or `.ods`
+/// format.
+///
+/// If any column is mapped by header, the first row of the sheet is taken as the header row and
+/// is not read as a student. Otherwise the first row is still taken as the header row if its ID
+/// has no digits while the IDs below it do, e.g. `Student ID` above `00123`.
+///
+/// # Returns
+///
+/// Returns the students keyed by ID, along with a report of the problems found in the roster.
+/// Blank rows are skipped without being reported.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or parsed, if the configured delimiter or
+/// encoding is invalid, or if the header of the ID or name column cannot be found. The immediate
+/// sign-in column is optional, so a missing header is only reported for it.
+pub fn load_student_info(config: &Config) -> Result<(HashMap<Id, Student>, Report)> {
+    let student_data = &config.student_data;
+    let immediate_sign_in = &student_data.immediate_sign_in;
+    let rows = read_rows(config)?;
+
+    let columns = [
+        &student_data.id_column,
+        &student_data.name_column,
+        &immediate_sign_in.column,
+    ];
+    let by_header = columns.iter().any(|column| column.header().is_some());
+    let header_row_guessed = !by_header
+        && rows.split_first().is_some_and(|((_, first), rest)| {
+            let id_column = student_data.id_column.index().map_or(0, usize::from);
+            looks_like_header(first, rest, id_column)
+        });
+    let (header_row, rows) = match rows.split_first() {
+        Some((header_row, rows)) if by_header || header_row_guessed => (Some(header_row), rows),
+        _ => (None, rows.as_slice()),
+    };
+    let mut report = Report {
+        header_row: header_row.map(|(row_number, _)| *row_number),
+        header_row_guessed,
+        ..Default::default()
+    };
+    let [id_column, name_column, immediate_sign_in_column] = columns
+        .map(|column| column.resolve(header_row.map(|(_, row)| row.as_slice()), &mut report));
+    let id_column = id_column?;
+    let name_column = name_column?;
+    let immediate_sign_in_column = match immediate_sign_in_column {
+        Err(Error::MissingHeader { header }) => {
+            report.missing_headers.push((header, None));
+            None
+        }
+        column => Some(column?),
+    };
+    let mut students = HashMap::new();
+    let mut rows_by_id: HashMap<Id, Vec<u32>> = HashMap::new();
+    for (row_number, row) in rows {
+        if row.iter().all(is_blank) {
+            continue;
+        }
+
+        let id = row
+            .get(id_column)
+            .map(|cell| id_text(cell, &student_data.id_normalization))
+            .filter(|id| !id.trim().is_empty());
+        let Some(id) = id else {
+            report.empty_ids.push(*row_number);
+            continue;
+        };
+        let key = student_data.id_normalization.normalize(&id);
+
+        let name = row.get(name_column).map(cell_text).unwrap_or_default();
+        if name.trim().is_empty() {
+            report.missing_names.push(*row_number);
+        }
+
+        rows_by_id.entry(key.clone()).or_default().push(*row_number);
+        students.insert(
+            key,
+            Student {
+                id,
+                name,
+                immediate_sign_in: immediate_sign_in_column
+                    .and_then(|column| row.get(column))
+                    .is_some_and(|cell| immediate_sign_in.is_enabled(&cell_text(cell))),
+                row: *row_number,
+            },
+        );
+    }
+
+    report.duplicates = rows_by_id
+        .into_iter()
+        .filter(|(_, rows)| rows.len() > 1)
+        .collect();
+    report.duplicates.sort_unstable_by_key(|(_, rows)| rows[0]);
+
+    Ok((students, report))
+}
+
+/// Returns whether `first` looks like a row of headers rather than a student.
+///
+/// Headers are text, so the row is taken as headers when its ID has no digits while the ID of
+/// another row has some. Rosters whose IDs have no digits at all are always read in full.
+///
+/// # Arguments
+///
+/// * `first` - The first row of the sheet.
+/// * `rest` - The other rows of the sheet.
+/// * `id_column` - The index of the ID column.
+fn looks_like_header(first: &[Data], rest: &[(u32, Vec<Data>)], id_column: usize) -> bool {
+    let has_digits = |row: &[Data]| {
+        row.get(id_column)
+            .is_some_and(|cell| cell_text(cell).chars().any(|c| c.is_ascii_digit()))
+    };
+
+    first.get(id_column).is_some_and(|cell| !is_blank(cell))
+        && !has_digits(first)
+        && rest.iter().any(|(_, row)| has_digits(row))
 }
 
-pub fn load_student_info_from_xlsx(config: &Config) -> Result<HashMap<Id, Student>> {
-    let mut workbook: Xlsx<_> = open_workbook(&config.student_data.filepath)?;
+/// Reads the headers of the configured sheet, which is its first row.
+///
+/// # Returns
+///
+/// Returns the non-empty headers, in column order.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or parsed.
+pub fn load_headers(config: &Config) -> Result<Vec<String>> {
+    let rows = read_rows(config)?;
+
+    Ok(rows
+        .first()
+        .map(|(_, row)| {
+            row.iter()
+                .map(|cell| cell_text(cell).trim().to_owned())
+                .filter(|header| !header.is_empty())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Reads every row of the configured sheet, each paired with its 1-based row number.
+fn read_rows(config: &Config) -> Result<Vec<(u32, Vec<Data>)>> {
+    let extension = config
+        .student_data
+        .filepath
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+
+    match extension.as_deref() {
+        Some("csv") => read_rows_from_csv(config, b','),
+        Some("tsv") => read_rows_from_csv(config, b'\t'),
+        _ => read_rows_from_workbook(config),
+    }
+}
+
+/// Reads the rows of a workbook, detecting its format with `calamine`.
+fn read_rows_from_workbook(config: &Config) -> Result<Vec<(u32, Vec<Data>)>> {
+    let mut workbook = open_workbook_auto(&config.student_data.filepath)?;
     let worksheet = workbook.worksheet_range(&config.student_data.sheet_name)?;
+    let first_row = worksheet.start().map_or(0, |(row, _)| row);
+
+    Ok((first_row + 1..)
+        .zip(worksheet.rows().map(<[Data]>::to_vec))
+        .collect())
+}
 
-    Ok(worksheet
-        .rows()
-        .filter(|row| row.get(config.student_data.id_column as usize).is_some())
-        .map(|row| {
-            (
-                row.get(config.student_data.id_column as usize)
-                    .unwrap()
-                    .to_string(),
-                Student {
-                    name: row
-                        .get(config.student_data.name_column as usize)
-                        .unwrap_or(&calamine::Data::String("".to_owned()))
-                        .to_string(),
-                    immediate_sign_in: row
-                        .get(config.student_data.immediate_sign_in.column as usize)
-                        .unwrap_or(&calamine::Data::Bool(false))
-                        .get_bool()
-                        .unwrap_or(false),
-                },
-            )
+/// Returns the configured delimiter of CSV and TSV files, or `default` if none is configured.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidDelimiter` if the configured delimiter is not an ASCII character.
+pub fn csv_delimiter(student_data: &StudentData, default: u8) -> Result<u8> {
+    match student_data.delimiter {
+        Some(delimiter) => u8::try_from(delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or(Error::InvalidDelimiter { delimiter }),
+        None => Ok(default),
+    }
+}
+
+/// Returns the configured character encoding of CSV and TSV files, or UTF-8 if none is
+/// configured.
+///
+/// # Errors
+///
+/// Returns `Error::UnknownEncoding` if the configured encoding is not recognised.
+pub fn csv_encoding(student_data: &StudentData) -> Result<&'static Encoding> {
+    match &student_data.encoding {
+        Some(label) => {
+            Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| Error::UnknownEncoding {
+                label: label.clone(),
+            })
+        }
+        None => Ok(UTF_8),
+    }
+}
+
+/// Reads the rows of a delimited text file.
+///
+/// # Arguments
+///
+/// * `config` - The application configuration.
+/// * `default_delimiter` - The delimiter used when none is configured.
+fn read_rows_from_csv(config: &Config, default_delimiter: u8) -> Result<Vec<(u32, Vec<Data>)>> {
+    let student_data = &config.student_data;
+    let delimiter = csv_delimiter(student_data, default_delimiter)?;
+    let encoding = csv_encoding(student_data)?;
+
+    // a byte order mark overrides the configured encoding
+    let bytes = fs::read(&student_data.filepath)?;
+    let (contents, _, had_errors) = encoding.decode(&bytes);
+    if had_errors {
+        eprintln!(
+            "Some characters in '{}' are not valid {}, they have been replaced",
+            student_data.filepath.display(),
+            encoding.name()
+        );
+    }
+
+    let rows = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(contents.as_bytes())
+        .into_records()
+        .map(|record| {
+            record.map(|record| {
+                record
+                    .iter()
+                    .map(|field| match field {
+                        "" => Data::Empty,
+                        field => Data::String(field.to_owned()),
+                    })
+                    .collect::<Vec<Data>>()
+            })
         })
-        .collect::<HashMap<Id, Student>>())
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok((1..).zip(rows).collect())
+}
+
+/// Returns the rows of the students that are allowed to sign in immediately, in ascending order.
+pub fn immediate_sign_in_rows(roster: &HashMap<Id, Student>) -> Vec<u32> {
+    let mut rows: Vec<u32> = roster
+        .values()
+        .filter(|student| student.immediate_sign_in)
+        .map(|student| student.row)
+        .collect();
+    rows.sort_unstable();
+    rows
+}
+
+/// How close a numeric cell has to be to a whole number to be written as one.
+const INTEGER_TOLERANCE: f64 = 1e-9;
+
+/// Returns the ID held by a cell, as it is written in the roster.
+///
+/// Numeric cells are read as floats, so with `integer_floats` set, a cell holding e.g.
+/// `1233.9999999999998` is read as `1234`. The other normalization rules are not applied.
+fn id_text(cell: &Data, normalization: &IdNormalization) -> Id {
+    match cell {
+        Data::Float(value)
+            if normalization.integer_floats
+                && value.is_finite()
+                && (value - value.round()).abs() < INTEGER_TOLERANCE =>
+        {
+            format!("{:.0}", value.round())
+        }
+        cell => cell_text(cell),
+    }
+}
+
+/// Returns whether a cell is empty or holds only whitespace.
+fn is_blank(cell: &Data) -> bool {
+    match cell {
+        Data::Empty => true,
+        Data::String(text) => text.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Returns the text of a cell as it is shown in a spreadsheet application.
+///
+/// Boolean cells are written as `TRUE` and `FALSE`, matching how Excel displays them.
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Bool(true) => "TRUE".to_owned(),
+        Data::Bool(false) => "FALSE".to_owned(),
+        cell => cell.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_xlsxwriter::Workbook;
+    use tempfile::{TempDir, tempdir};
+
+    use super::*;
+
+    /// Writes `contents` to a CSV file and returns a configuration that reads it.
+    fn roster_config(contents: &str) -> (TempDir, Config) {
+        roster_file_config("roster.csv", contents.as_bytes())
+    }
+
+    /// Writes `contents` to a file named `name` and returns a configuration that reads it.
+    fn roster_file_config(name: &str, contents: &[u8]) -> (TempDir, Config) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+
+        let mut config = Config::default();
+        config.student_data.filepath = path;
+        config.student_data.id_column = Column::Index(0);
+        config.student_data.name_column = Column::Index(1);
+        config.student_data.immediate_sign_in.column = Column::Index(2);
+        (dir, config)
+    }
+
+    #[test]
+    fn header_row_is_skipped_when_columns_are_mapped_by_index() {
+        let (_dir, config) = roster_config("Student ID,Name,Sign in\n00123,Aiko,\n00124,Ben,\n");
+
+        let (roster, report) = load_student_info(&config).unwrap();
+
+        assert_eq!(roster.len(), 2);
+        assert!(!roster.values().any(|student| student.name == "Name"));
+        assert_eq!(report.header_row, Some(1));
+        assert!(report.header_row_guessed);
+        assert!(report.to_string().starts_with("Row 1 has no ID"));
+    }
+
+    #[test]
+    fn missing_immediate_sign_in_header_is_reported() {
+        let (_dir, mut config) = roster_config("Student ID,Name\n00123,Aiko\n");
+        config.student_data.id_column = Column::Header("Student ID".to_owned());
+        config.student_data.name_column = Column::Header("Name".to_owned());
+        config.student_data.immediate_sign_in.column = Column::Header("Sign in".to_owned());
+
+        let (roster, report) = load_student_info(&config).unwrap();
+
+        assert_eq!(roster.len(), 1);
+        assert!(!roster["00123"].immediate_sign_in);
+        assert_eq!(report.missing_headers, [("Sign in".to_owned(), None)]);
+        assert!(report.has_problems());
+    }
+
+    #[test]
+    fn boolean_cells_enable_immediate_sign_in_as_displayed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("roster.xlsx");
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet().set_name("Roster").unwrap();
+        sheet.write_string(0, 0, "00123").unwrap();
+        sheet.write_string(0, 1, "Aiko").unwrap();
+        sheet.write_boolean(0, 2, true).unwrap();
+        sheet.write_string(1, 0, "00124").unwrap();
+        sheet.write_string(1, 1, "Ben").unwrap();
+        sheet.write_boolean(1, 2, false).unwrap();
+        workbook.save(&path).unwrap();
+
+        let mut config = Config::default();
+        config.student_data.filepath = path;
+        config.student_data.sheet_name = "Roster".to_owned();
+        config.student_data.id_column = Column::Index(0);
+        config.student_data.name_column = Column::Index(1);
+        config.student_data.immediate_sign_in.column = Column::Index(2);
+        let (roster, _) = load_student_info(&config).unwrap();
+        assert!(roster["00123"].immediate_sign_in);
+        assert!(!roster["00124"].immediate_sign_in);
+
+        config.student_data.immediate_sign_in.enabled_symbol = "FALSE".to_owned();
+        let (roster, _) = load_student_info(&config).unwrap();
+        assert!(!roster["00123"].immediate_sign_in);
+        assert!(roster["00124"].immediate_sign_in);
+    }
+
+    #[test]
+    fn immediate_sign_in_column_past_the_row_signs_no_one_in() {
+        let (_dir, mut config) = roster_config("00123,Aiko,TRUE\n00124,Ben\n");
+        config.student_data.immediate_sign_in.column = Column::Index(5);
+
+        let (roster, report) = load_student_info(&config).unwrap();
+
+        assert_eq!(roster.len(), 2);
+        assert!(!roster.values().any(|student| student.immediate_sign_in));
+        assert!(!report.has_problems());
+    }
+
+    #[test]
+    fn missing_id_header_stops_the_roster_from_loading() {
+        let (_dir, mut config) = roster_config("ID,Name\n00123,Aiko\n");
+        config.student_data.id_column = Column::Header("Student ID".to_owned());
+
+        let result = load_student_info(&config);
+
+        assert!(matches!(result, Err(Error::MissingHeader { header }) if header == "Student ID"));
+    }
+
+    #[test]
+    fn roster_without_headers_is_read_in_full() {
+        let (_dir, config) = roster_config("00123,Aiko,\n00124,Ben,\n");
+
+        let (roster, report) = load_student_info(&config).unwrap();
+
+        assert_eq!(roster.len(), 2);
+        assert_eq!(report.header_row, None);
+        assert!(!report.header_row_guessed);
+    }
+
+    #[test]
+    fn roster_of_ids_without_digits_is_read_in_full() {
+        let (_dir, config) = roster_config("aiko,Aiko,\nben,Ben,\n");
+
+        let (roster, report) = load_student_info(&config).unwrap();
+
+        assert_eq!(roster.len(), 2);
+        assert_eq!(report.header_row, None);
+    }
+
+    #[test]
+    fn csv_roster_is_decoded_with_the_configured_encoding() {
+        let (contents, _, _) = encoding_rs::SHIFT_JIS.encode("00123,山田,\n");
+        let (_dir, mut config) = roster_file_config("roster.csv", &contents);
+        config.student_data.encoding = Some("Shift_JIS".to_owned());
+        let (roster, _) = load_student_info(&config).unwrap();
+        assert_eq!(roster["00123"].name, "山田");
+
+        let (_dir, mut config) = roster_file_config("roster.csv", b"00123,Zo\xeb,\n");
+        config.student_data.encoding = Some(" windows-1252 ".to_owned());
+        let (roster, _) = load_student_info(&config).unwrap();
+        assert_eq!(roster["00123"].name, "Zoë");
+    }
+
+    #[test]
+    fn unknown_encoding_stops_the_roster_from_loading() {
+        let (_dir, mut config) = roster_config("00123,Aiko,\n");
+        config.student_data.encoding = Some("klingon".to_owned());
+
+        let result = load_student_info(&config);
+
+        assert!(matches!(result, Err(Error::UnknownEncoding { label }) if label == "klingon"));
+    }
+
+    #[test]
+    fn tsv_roster_is_split_on_tabs() {
+        let (_dir, config) = roster_file_config("roster.TSV", b"00123\tYamada, Aiko\t\n");
+
+        let (roster, _) = load_student_info(&config).unwrap();
+
+        assert_eq!(roster["00123"].name, "Yamada, Aiko");
+    }
+
+    #[test]
+    fn configured_delimiter_replaces_the_one_of_the_extension() {
+        let (_dir, mut config) = roster_config("00123;Yamada, Aiko;\n");
+        config.student_data.delimiter = Some(';');
+        let (roster, _) = load_student_info(&config).unwrap();
+        assert_eq!(roster["00123"].name, "Yamada, Aiko");
+
+        config.student_data.delimiter = Some('、');
+        let result = load_student_info(&config);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidDelimiter { delimiter: '、' })
+        ));
+    }
 }
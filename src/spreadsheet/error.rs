@@ -9,18 +9,78 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// Represents errors that can occur in spreadsheet operations.
 ///
 /// This enum encompasses various error types that might arise during
-/// spreadsheet interactions, such as borrowing errors or Excel file processing issues.
+/// spreadsheet interactions, such as unreadable files or columns that cannot be found.
 ///
 /// # Variants
 ///
-/// * `Borrow` - Represents errors that occur when attempting to borrow data.
-/// * `Xlsx` - Represents errors related to Excel file operations.
+/// * `Xlsx` - Represents errors when reading an Excel 2007+ (`.xlsx`) workbook.
+/// * `Xls` - Represents errors when reading a legacy Excel (`.xls`) workbook.
+/// * `Xlsb` - Represents errors when reading an Excel binary (`.xlsb`) workbook.
+/// * `Ods` - Represents errors when reading an OpenDocument (`.ods`) spreadsheet.
+/// * `Workbook` - Represents other errors when opening a workbook, such as an unrecognised format.
+/// * `Io` - Represents I/O errors when reading a roster file.
+/// * `Csv` - Represents errors when parsing a CSV or TSV file.
+/// * `InvalidDelimiter` - Indicates that the configured delimiter is not a single ASCII character.
+/// * `UnknownEncoding` - Indicates that the configured encoding is not recognised.
+/// * `MissingHeader` - Indicates that a column is mapped by a header the sheet does not have.
 #[derive(Debug, Error)]
 pub enum Error {
     /// Represents errors related to Excel file operations.
     ///
     /// This variant wraps `calamine::XlsxError` and is used when operations
     /// like reading from or parsing Excel files fail.
-    #[error(transparent)]
+    #[error("Cannot read the Excel workbook: {0}")]
     Xlsx(#[from] calamine::XlsxError),
+
+    /// Represents errors when reading a workbook in the legacy Excel 97-2003 format.
+    #[error("Cannot read the Excel 97-2003 workbook: {0}")]
+    Xls(#[from] calamine::XlsError),
+
+    /// Represents errors when reading a workbook in the Excel binary format.
+    #[error("Cannot read the Excel binary workbook: {0}")]
+    Xlsb(#[from] calamine::XlsbError),
+
+    /// Represents errors when reading an OpenDocument spreadsheet, such as one saved by
+    /// LibreOffice Calc.
+    #[error("Cannot read the OpenDocument spreadsheet: {0}")]
+    Ods(#[from] calamine::OdsError),
+
+    /// Represents any other error reported by `calamine` when opening a workbook, for example
+    /// when its format cannot be detected.
+    #[error("Cannot read the workbook: {0}")]
+    Workbook(calamine::Error),
+
+    /// Represents I/O errors that occur when reading a roster file.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Represents errors when parsing a CSV or TSV file, originating from the `csv` library.
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    /// Indicates that the configured delimiter cannot be used to split a CSV file.
+    #[error("The delimiter '{delimiter}' is not a single ASCII character.")]
+    InvalidDelimiter { delimiter: char },
+
+    /// Indicates that the configured encoding is not a known character encoding.
+    #[error("Unknown character encoding '{label}'.")]
+    UnknownEncoding { label: String },
+
+    /// Indicates that no column in the first row of the sheet has the configured header.
+    #[error("No column has the header '{header}'.")]
+    MissingHeader { header: String },
+}
+
+impl From<calamine::Error> for Error {
+    /// Unwraps the format-specific errors so that they keep their own variant.
+    fn from(error: calamine::Error) -> Self {
+        match error {
+            calamine::Error::Io(e) => Error::Io(e),
+            calamine::Error::Xlsx(e) => Error::Xlsx(e),
+            calamine::Error::Xls(e) => Error::Xls(e),
+            calamine::Error::Xlsb(e) => Error::Xlsb(e),
+            calamine::Error::Ods(e) => Error::Ods(e),
+            e => Error::Workbook(e),
+        }
+    }
 }
@@ -1,19 +1,66 @@
-use std::{cell::RefCell, rc::Rc, time::Duration};
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use slint::{
-    CloseRequestResponse, SharedString, Timer, ToSharedString, Weak, language::ColorScheme,
+    CloseRequestResponse, ModelRc, SharedString, Timer, TimerMode, ToSharedString, VecModel, Weak,
+    language::ColorScheme,
 };
+use time::OffsetDateTime;
 
-use crate::config::{Config, Theme};
+use crate::attendance::{AttendanceLog, Outcome};
+use crate::check_in::Desk;
+use crate::config::{Config, IdCase, Location, Theme};
+use crate::export;
+use crate::pin_lockout::{Attempt, PinLockout};
+use crate::roster::{Reload, RosterWatcher};
+use crate::scanner::ScanDetector;
+use crate::spreadsheet::{self, Column, immediate_sign_in_rows};
 
 slint::include_modules!();
 
-pub fn init(config: &Rc<RefCell<Config>>) -> App {
+/// How long the result of a check-in stays on the home page before it is cleared.
+const CHECK_IN_RESULT_DURATION: Duration = Duration::from_secs(5);
+/// The header choice that maps a column by its index instead of its header.
+const COLUMN_BY_INDEX: &str = "By column number";
+/// How long a student has to confirm their name before the home page is cleared.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(20);
+/// How many characters of a name have to be typed before any students are suggested.
+const SEARCH_MIN_LENGTH: usize = 2;
+/// How many students are suggested when searching by name.
+const SEARCH_RESULT_LIMIT: usize = 5;
+/// How far back the settings page lists queued sign-ins that have to be entered in MyStudio by
+/// hand.
+const UNSYNCED_PERIOD: time::Duration = time::Duration::days(30);
+
+pub fn init(
+    config: &Rc<RefCell<Config>>,
+    location: &Location,
+    desk: &Desk,
+    roster_watcher: &RosterWatcher,
+) -> App {
     let ui = App::new().unwrap();
     slint::set_xdg_app_id("youkoso").unwrap();
-    impl_home_page_callbacks(&ui, config);
-    impl_settings_page_callbacks(&ui, config);
+    impl_home_page_callbacks(&ui, config, desk);
+    impl_settings_page_callbacks(&ui, config, desk.log());
     load_config(&ui, &config.try_borrow().unwrap());
+    ui.global::<Settings>()
+        .set_config_location(location.to_string().into());
+    if let Some(reload) = roster_watcher.last_reload() {
+        show_roster_status(&ui, desk, &reload);
+    }
+    roster_watcher.on_reload({
+        let ui = ui.as_weak();
+        let desk = desk.clone();
+        move |reload| {
+            if let Some(strong_ui) = ui.upgrade() {
+                show_roster_status(&strong_ui, &desk, reload);
+            }
+        }
+    });
 
     ui.window().on_close_requested({
         let ui = ui.as_weak();
@@ -26,8 +73,18 @@ pub fn init(config: &Rc<RefCell<Config>>) -> App {
                 save_to_config(&strong_ui, &mut config);
                 settings.set_syncing(false);
             }
-            config.save().unwrap();
+            // the window closes either way, so the error can only be logged
+            if let Err(e) = config.save() {
+                eprintln!("Error when saving settings: {e}");
+            }
+            if config.has_unsaved_credentials() {
+                eprintln!("The MyStudio email was not saved, since no secret store is available");
+            }
 
+            // kiosk mode has to be left through the settings page, which needs the admin PIN
+            if settings.get_kiosk().lockdown {
+                return CloseRequestResponse::KeepWindowShown;
+            }
             CloseRequestResponse::HideWindow
         }
     });
@@ -35,11 +92,432 @@ pub fn init(config: &Rc<RefCell<Config>>) -> App {
     ui
 }
 
-fn impl_home_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>) {}
+fn impl_home_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>, desk: &Desk) {
+    let home = ui.global::<Home>();
+    // shared so that a new check-in restarts the countdown instead of being cleared early
+    let clear_timer = Rc::new(Timer::default());
+
+    home.on_submit({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        let desk = desk.clone();
+        let clear_timer = Rc::clone(&clear_timer);
+        move |id: SharedString| {
+            let id = config
+                .try_borrow()
+                .unwrap()
+                .student_data
+                .id_normalization
+                .normalize(&id);
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+
+            match desk.find(&id) {
+                Some(student) if !student.immediate_sign_in => {
+                    ask_for_confirmation(&strong_ui, &clear_timer, &id, &student.name);
+                }
+                _ => check_in(&strong_ui, &config, &desk, &clear_timer, id),
+            }
+        }
+    });
+
+    home.on_scan_key({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        let detector = RefCell::new(ScanDetector::default());
+        move |text: SharedString| {
+            let scanned = detector.borrow_mut().key(
+                &text,
+                Instant::now(),
+                &config.try_borrow().unwrap().kiosk.scanner,
+            );
+            let Some(code) = scanned else {
+                return false;
+            };
+
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            strong_ui.global::<Home>().invoke_submit(code.into());
+            true
+        }
+    });
 
-fn impl_settings_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>) {
+    home.on_search({
+        let ui = ui.as_weak();
+        let desk = desk.clone();
+        move |query: SharedString| {
+            let results: Vec<SearchResult> = if query.trim().chars().count() < SEARCH_MIN_LENGTH {
+                Vec::new()
+            } else {
+                desk.search(&query, SEARCH_RESULT_LIMIT)
+                    .into_iter()
+                    .map(|(id, student)| SearchResult {
+                        id: id.into(),
+                        name: student.name.into(),
+                    })
+                    .collect()
+            };
+
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            strong_ui
+                .global::<Home>()
+                .set_search_results(ModelRc::new(VecModel::from(results)));
+        }
+    });
+
+    home.on_confirm({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        let desk = desk.clone();
+        let clear_timer = Rc::clone(&clear_timer);
+        move || {
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            let home = strong_ui.global::<Home>();
+            if home.get_state() != CheckInState::Confirming {
+                return;
+            }
+
+            let id = home.get_pending_id().to_string();
+            check_in(&strong_ui, &config, &desk, &clear_timer, id);
+        }
+    });
+
+    home.on_dismiss_greeting({
+        let ui = ui.as_weak();
+        let clear_timer = Rc::clone(&clear_timer);
+        move || {
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            clear_timer.stop();
+            clear_home_page(&strong_ui);
+        }
+    });
+
+    home.on_cancel({
+        let ui = ui.as_weak();
+        move || {
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            clear_timer.stop();
+            clear_home_page(&strong_ui);
+        }
+    });
+}
+
+/// Asks the student to confirm their name before they are signed in, and gives up after
+/// `CONFIRMATION_TIMEOUT`.
+fn ask_for_confirmation(ui: &App, clear_timer: &Timer, id: &str, name: &str) {
+    let home = ui.global::<Home>();
+    home.set_state(CheckInState::Confirming);
+    home.set_pending_id(id.into());
+    home.set_message(format!("Sign in as {name}?").into());
+
+    let ui = ui.as_weak();
+    let id = SharedString::from(id);
+    clear_timer.start(TimerMode::SingleShot, CONFIRMATION_TIMEOUT, move || {
+        if let Some(strong_ui) = ui.upgrade() {
+            clear_home_page_of(&strong_ui, &id);
+        }
+    });
+}
+
+/// Signs in the student with the given ID and shows the outcome on the home page, greeting the
+/// student if they were signed in.
+fn check_in(
+    ui: &App,
+    config: &Rc<RefCell<Config>>,
+    desk: &Desk,
+    clear_timer: &Rc<Timer>,
+    id: String,
+) {
+    clear_timer.stop();
+    let home = ui.global::<Home>();
+    home.set_state(CheckInState::Pending);
+    home.set_pending_id(id.as_str().into());
+    home.set_message(match desk.find(&id) {
+        Some(student) => format!("Signing in {}...", student.name).into(),
+        None => "Signing in...".into(),
+    });
+
+    let ui = ui.as_weak();
+    let config = Rc::clone(config);
+    let desk = desk.clone();
+    let clear_timer = Rc::clone(clear_timer);
+    slint::spawn_local(async move {
+        let record = desk.check_in(&id).await;
+        let Some(strong_ui) = ui.upgrade() else {
+            return;
+        };
+        // the check-in is logged regardless, but if another student has taken over the home page
+        // in the meantime, showing this result would replace theirs
+        let home = strong_ui.global::<Home>();
+        if home.get_state() != CheckInState::Pending || home.get_pending_id() != id.as_str() {
+            return;
+        }
+
+        let name = record.student_name.unwrap_or_default();
+        let (state, message) = match record.outcome {
+            Outcome::CheckedIn | Outcome::Synced => {
+                (CheckInState::Success, format!("Welcome, {name}!"))
+            }
+            Outcome::Queued | Outcome::NeedsReconciliation => (
+                CheckInState::Offline,
+                format!("Welcome, {name}! Recorded offline, will sync."),
+            ),
+            Outcome::UnknownId => (
+                CheckInState::UnknownId,
+                format!("No student found with ID '{id}'."),
+            ),
+            Outcome::Unconfirmed { .. } => (
+                CheckInState::Failed,
+                "MyStudio did not confirm the sign-in. Please ask at the front desk before trying \
+                 again."
+                    .to_owned(),
+            ),
+            Outcome::Failed { error } => (CheckInState::Failed, format!("Sign-in failed: {error}")),
+        };
+
+        let config = config.try_borrow().unwrap();
+        let greeting = &config.kiosk.greeting;
+        let duration = match state {
+            CheckInState::Success | CheckInState::Offline if greeting.duration_secs > 0 => {
+                let today = record.timestamp.date();
+                show_greeting(
+                    &strong_ui,
+                    &name,
+                    greeting.message_for(&record.student_id, today),
+                );
+                Duration::from_secs(greeting.duration_secs)
+            }
+            _ => CHECK_IN_RESULT_DURATION,
+        };
+        show_check_in_result(&strong_ui, &clear_timer, &id, state, message, duration);
+    })
+    .unwrap();
+}
+
+/// Shows the full-screen greeting for a student who has just signed in. It is hidden again when
+/// the home page is cleared.
+fn show_greeting(ui: &App, name: &str, message: Option<&str>) {
+    let home = ui.global::<Home>();
+    home.set_greeting_name(name.into());
+    home.set_greeting_message(message.unwrap_or_default().into());
+    home.set_greeting_shown(true);
+}
+
+/// Displays the outcome of the check-in of `id` on the home page and clears it after `duration`.
+fn show_check_in_result(
+    ui: &App,
+    clear_timer: &Timer,
+    id: &str,
+    state: CheckInState,
+    message: String,
+    duration: Duration,
+) {
+    let home = ui.global::<Home>();
+    home.set_state(state);
+    home.set_message(message.into());
+
+    let ui = ui.as_weak();
+    let id = SharedString::from(id);
+    clear_timer.start(TimerMode::SingleShot, duration, move || {
+        if let Some(strong_ui) = ui.upgrade() {
+            clear_home_page_of(&strong_ui, &id);
+        }
+    });
+}
+
+/// Clears the home page if it still shows the check-in of `id`, so that a timer started for one
+/// student never clears the page for the next.
+fn clear_home_page_of(ui: &App, id: &str) {
+    if ui.global::<Home>().get_pending_id() == id {
+        clear_home_page(ui);
+    }
+}
+
+/// Returns the home page to its idle state, ready for the next student.
+fn clear_home_page(ui: &App) {
+    let home = ui.global::<Home>();
+    home.set_state(CheckInState::Idle);
+    home.set_message(SharedString::new());
+    home.set_pending_id(SharedString::new());
+    home.set_greeting_shown(false);
+}
+
+fn impl_settings_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>, log: &AttendanceLog) {
     let settings = ui.global::<Settings>();
 
+    settings.on_export_attendance({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        move || {
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            let settings = strong_ui.global::<Settings>();
+
+            let dates = export::parse_date(&settings.get_export_from())
+                .and_then(|from| Ok((from, export::parse_date(&settings.get_export_to())?)));
+            let status = match dates {
+                Ok((from, to)) => {
+                    let path = PathBuf::from(settings.get_export_path().as_str());
+                    match export::export(&config.try_borrow().unwrap(), from, to, None, &path) {
+                        Ok(count) => format!("Exported {count} sign-ins to '{}'.", path.display()),
+                        Err(e) => format!("Export failed: {e}"),
+                    }
+                }
+                Err(e) => format!("Export failed: invalid date, {e}"),
+            };
+            settings.set_export_status(status.into());
+        }
+    });
+
+    settings.on_refresh_attendance({
+        let ui = ui.as_weak();
+        let log = log.clone();
+        move || {
+            let today = OffsetDateTime::now_local()
+                .unwrap_or_else(|_| OffsetDateTime::now_utc())
+                .date();
+            let records = log.query(today..=today, None).unwrap_or_else(|e| {
+                eprintln!("Error when reading the attendance log: {e}");
+                Vec::new()
+            });
+
+            let entries: Vec<AttendanceEntry> = records
+                .iter()
+                .rev()
+                .map(|record| AttendanceEntry {
+                    time: format!(
+                        "{:02}:{:02}",
+                        record.timestamp.hour(),
+                        record.timestamp.minute()
+                    )
+                    .into(),
+                    student_id: record.student_id.clone().into(),
+                    name: record.student_name.clone().unwrap_or_default().into(),
+                    outcome: record.outcome.to_shared_string(),
+                })
+                .collect();
+
+            let unsynced: Vec<AttendanceEntry> = log
+                .query(today.saturating_sub(UNSYNCED_PERIOD)..=today, None)
+                .unwrap_or_default()
+                .iter()
+                .filter(|record| record.outcome == Outcome::NeedsReconciliation)
+                .map(|record| AttendanceEntry {
+                    time: format!(
+                        "{} {:02}:{:02}",
+                        record.timestamp.date(),
+                        record.timestamp.hour(),
+                        record.timestamp.minute()
+                    )
+                    .into(),
+                    student_id: record.student_id.clone().into(),
+                    name: record.student_name.clone().unwrap_or_default().into(),
+                    outcome: record.outcome.to_shared_string(),
+                })
+                .collect();
+
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            let settings = strong_ui.global::<Settings>();
+            settings.set_attendance_today(ModelRc::new(VecModel::from(entries)));
+            settings.set_unsynced_sign_ins(ModelRc::new(VecModel::from(unsynced)));
+        }
+    });
+
+    settings.on_refresh_headers({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        move || {
+            let config = config.try_borrow().unwrap();
+            let mut choices = vec![COLUMN_BY_INDEX.to_shared_string()];
+            // there is nothing to read until a file is configured
+            if !config.student_data.filepath.as_os_str().is_empty() {
+                match spreadsheet::load_headers(&config) {
+                    Ok(headers) => choices.extend(headers.into_iter().map(SharedString::from)),
+                    Err(e) => eprintln!("Error when reading the student data headers: {e}"),
+                }
+            }
+
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            strong_ui
+                .global::<Settings>()
+                .set_header_choices(ModelRc::new(VecModel::from(choices)));
+        }
+    });
+
+    settings.on_verify_admin_pin({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        // shared by every PIN prompt, so that switching prompts does not reset the count
+        let lockout = RefCell::new(PinLockout::default());
+        move |pin: SharedString| {
+            let config = config.try_borrow().unwrap();
+            let attempt = lockout
+                .borrow_mut()
+                .attempt(Instant::now(), || config.kiosk.verify_admin_pin(&pin));
+            let error = match attempt {
+                Attempt::Accepted => return true,
+                Attempt::Wrong => "Wrong PIN".to_owned(),
+                Attempt::Locked(wait) => format!(
+                    "Too many wrong PINs. Try again in {} seconds.",
+                    wait.as_secs_f64().ceil()
+                ),
+            };
+
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            strong_ui
+                .global::<Settings>()
+                .set_admin_pin_error(error.into());
+            false
+        }
+    });
+
+    settings.on_set_admin_pin({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        move |pin: SharedString| {
+            let mut config = config.try_borrow_mut().unwrap();
+            let status = match config.kiosk.set_admin_pin(&pin) {
+                Ok(()) => match config.save() {
+                    Ok(()) => "The admin PIN has been saved.".to_owned(),
+                    Err(e) => format!("The admin PIN is set but could not be saved: {e}"),
+                },
+                Err(e) => e.to_string(),
+            };
+
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            let settings = strong_ui.global::<Settings>();
+            settings.set_admin_pin_set(config.kiosk.has_admin_pin());
+            settings.set_admin_pin_status(status.into());
+        }
+    });
+
+    settings.on_remove_admin_pin({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        move || {
+            let mut config = config.try_borrow_mut().unwrap();
+            config.kiosk.admin_pin_hash = None;
+            let status = match config.save() {
+                Ok(()) => "The admin PIN has been removed.".to_owned(),
+                Err(e) => format!("The admin PIN is removed but could not be saved: {e}"),
+            };
+
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            let settings = strong_ui.global::<Settings>();
+            settings.set_admin_pin_set(false);
+            settings.set_admin_pin_status(status.into());
+        }
+    });
+
     settings.on_sync_settings({
         let ui = ui.as_weak();
         let config = Rc::clone(config);
@@ -60,19 +538,73 @@ fn impl_settings_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>) {
             Timer::single_shot(Duration::from_secs(5), move || {
                 // a strong reference to the ui
                 let strong_ui = ui.upgrade().unwrap();
-                let mut config = config.try_borrow_mut().unwrap();
-                save_to_config(&strong_ui, &mut config);
+                let settings = strong_ui.global::<Settings>();
+                {
+                    let mut config = config.try_borrow_mut().unwrap();
+                    save_to_config(&strong_ui, &mut config);
+                    if let Err(e) = config.save() {
+                        eprintln!("Error when saving settings: {e}");
+                    }
+                    settings.set_backup_available(config.has_backup());
+                    settings.set_secret_store(secret_store_text(&config));
+                }
 
-                strong_ui.global::<Settings>().set_syncing(false);
+                settings.set_syncing(false);
+                // the file or sheet may have changed
+                settings.invoke_refresh_headers();
             });
         }
     });
 
+    settings.on_restore_backup({
+        let ui = ui.as_weak();
+        let config = Rc::clone(config);
+        move || {
+            // a strong reference to the ui
+            let strong_ui = ui.upgrade().unwrap();
+            {
+                let mut config = config.try_borrow_mut().unwrap();
+                let status = match config.restore_backup() {
+                    Ok(replaced) => format!(
+                        "Restored the previous settings. The replaced settings were kept in '{}'.",
+                        replaced.display()
+                    ),
+                    Err(e) => {
+                        eprintln!("Error when restoring the previous settings: {e}");
+                        format!("Error when restoring the previous settings: {e}")
+                    }
+                };
+                load_config(&strong_ui, &config);
+                strong_ui
+                    .global::<Settings>()
+                    .set_backup_status(status.into());
+            }
+
+            // the restored settings may use another file or sheet
+            strong_ui.global::<Settings>().invoke_refresh_headers();
+        }
+    });
+
+    settings.on_overridden_by({
+        // the environment does not change while running, so neither do the overrides
+        let overrides = config.try_borrow().unwrap().overrides().clone();
+        move |id: SharedString| {
+            setting_key(&id)
+                .and_then(|key| overrides.variable(key))
+                .unwrap_or_default()
+                .into()
+        }
+    });
+
     settings.on_reset({
         let ui = ui.as_weak();
         let config = Rc::clone(config);
         move |id: SharedString| {
             let mut config = config.try_borrow_mut().unwrap();
+            // settings set by the environment cannot be changed, not even by resetting all
+            if setting_key(&id).is_some_and(|key| config.overrides().variable(key).is_some()) {
+                return;
+            }
             // a strong reference to the ui
             let strong_ui = ui.upgrade().unwrap();
             let settings = strong_ui.global::<Settings>();
@@ -111,24 +643,45 @@ fn impl_settings_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>) {
                     updated.sheet_name = config.student_data.sheet_name.clone().into();
                     settings.set_student_data(updated);
                 }
+                "student-data.delimiter" => {
+                    config.student_data.delimiter = Config::default().student_data.delimiter;
+                    let mut updated = settings.get_student_data();
+                    updated.delimiter = delimiter_text(config.student_data.delimiter);
+                    settings.set_student_data(updated);
+                }
+                "student-data.encoding" => {
+                    config.student_data.encoding = Config::default().student_data.encoding;
+                    let mut updated = settings.get_student_data();
+                    updated.encoding = config
+                        .student_data
+                        .encoding
+                        .clone()
+                        .unwrap_or_default()
+                        .into();
+                    settings.set_student_data(updated);
+                }
                 "student-data.name-column" => {
                     config.student_data.name_column = Config::default().student_data.name_column;
                     let mut updated = settings.get_student_data();
-                    updated.name_column = config.student_data.name_column.into();
+                    (updated.name_column, updated.name_header) =
+                        column_fields(&config.student_data.name_column);
                     settings.set_student_data(updated);
                 }
                 "student-data.id-column" => {
                     config.student_data.id_column = Config::default().student_data.id_column;
                     let mut updated = settings.get_student_data();
-                    updated.id_column = config.student_data.id_column.into();
+                    (updated.id_column, updated.id_header) =
+                        column_fields(&config.student_data.id_column);
                     settings.set_student_data(updated);
                 }
                 "student-data.immediate-sign-in-column" => {
                     config.student_data.immediate_sign_in.column =
                         Config::default().student_data.immediate_sign_in.column;
                     let mut updated = settings.get_student_data();
-                    updated.immediate_sign_in_column =
-                        config.student_data.immediate_sign_in.column.into();
+                    (
+                        updated.immediate_sign_in_column,
+                        updated.immediate_sign_in_header,
+                    ) = column_fields(&config.student_data.immediate_sign_in.column);
                     settings.set_student_data(updated);
                 }
                 "student-data.immediate-sign-in-enabled-symbol" => {
@@ -145,6 +698,87 @@ fn impl_settings_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>) {
                         .into();
                     settings.set_student_data(updated);
                 }
+                "student-data.immediate-sign-in-case-sensitive" => {
+                    config.student_data.immediate_sign_in.case_sensitive = Config::default()
+                        .student_data
+                        .immediate_sign_in
+                        .case_sensitive;
+                    let mut updated = settings.get_student_data();
+                    updated.immediate_sign_in_case_sensitive =
+                        config.student_data.immediate_sign_in.case_sensitive;
+                    settings.set_student_data(updated);
+                }
+                "student-data.id-trim" => {
+                    config.student_data.id_normalization.trim =
+                        Config::default().student_data.id_normalization.trim;
+                    let mut updated = settings.get_student_data();
+                    updated.id_trim = config.student_data.id_normalization.trim;
+                    settings.set_student_data(updated);
+                }
+                "student-data.id-case" => {
+                    config.student_data.id_normalization.case =
+                        Config::default().student_data.id_normalization.case;
+                    let mut updated = settings.get_student_data();
+                    updated.id_case = id_case_text(config.student_data.id_normalization.case);
+                    settings.set_student_data(updated);
+                }
+                "student-data.id-strip-leading-zeros" => {
+                    config.student_data.id_normalization.strip_leading_zeros = Config::default()
+                        .student_data
+                        .id_normalization
+                        .strip_leading_zeros;
+                    let mut updated = settings.get_student_data();
+                    updated.id_strip_leading_zeros =
+                        config.student_data.id_normalization.strip_leading_zeros;
+                    settings.set_student_data(updated);
+                }
+                "student-data.id-integer-floats" => {
+                    config.student_data.id_normalization.integer_floats = Config::default()
+                        .student_data
+                        .id_normalization
+                        .integer_floats;
+                    let mut updated = settings.get_student_data();
+                    updated.id_integer_floats = config.student_data.id_normalization.integer_floats;
+                    settings.set_student_data(updated);
+                }
+                "kiosk.name-search" => {
+                    config.kiosk.name_search = Config::default().kiosk.name_search;
+                    let mut updated = settings.get_kiosk();
+                    updated.name_search = config.kiosk.name_search;
+                    settings.set_kiosk(updated);
+                }
+                "kiosk.scanner-enabled" => {
+                    config.kiosk.scanner.enabled = Config::default().kiosk.scanner.enabled;
+                    let mut updated = settings.get_kiosk();
+                    updated.scanner_enabled = config.kiosk.scanner.enabled;
+                    settings.set_kiosk(updated);
+                }
+                "kiosk.scanner-prefix" => {
+                    config.kiosk.scanner.prefix = Config::default().kiosk.scanner.prefix;
+                    let mut updated = settings.get_kiosk();
+                    updated.scanner_prefix = config.kiosk.scanner.prefix.clone().into();
+                    settings.set_kiosk(updated);
+                }
+                "kiosk.scanner-suffix" => {
+                    config.kiosk.scanner.suffix = Config::default().kiosk.scanner.suffix;
+                    let mut updated = settings.get_kiosk();
+                    updated.scanner_suffix = config.kiosk.scanner.suffix.clone().into();
+                    settings.set_kiosk(updated);
+                }
+                "kiosk.scanner-hide-id-field" => {
+                    config.kiosk.scanner.hide_id_field =
+                        Config::default().kiosk.scanner.hide_id_field;
+                    let mut updated = settings.get_kiosk();
+                    updated.scanner_hide_id_field = config.kiosk.scanner.hide_id_field;
+                    settings.set_kiosk(updated);
+                }
+                "kiosk.greeting-duration" => {
+                    config.kiosk.greeting.duration_secs =
+                        Config::default().kiosk.greeting.duration_secs;
+                    let mut updated = settings.get_kiosk();
+                    updated.greeting_duration = greeting_duration_field(&config);
+                    settings.set_kiosk(updated);
+                }
                 _ => eprintln!("unknown settings identifier"),
             }
         }
@@ -161,11 +795,25 @@ fn impl_settings_page_callbacks(ui: &App, config: &Rc<RefCell<Config>>) {
             settings.invoke_reset("my-studio.company-id".to_shared_string());
             settings.invoke_reset("student-data.filepath".to_shared_string());
             settings.invoke_reset("student-data.sheet-name".to_shared_string());
+            settings.invoke_reset("student-data.delimiter".to_shared_string());
+            settings.invoke_reset("student-data.encoding".to_shared_string());
             settings.invoke_reset("student-data.name-column".to_shared_string());
             settings.invoke_reset("student-data.id-column".to_shared_string());
             settings.invoke_reset("student-data.immediate-sign-in-column".to_shared_string());
             settings
                 .invoke_reset("student-data.immediate-sign-in-enabled-symbol".to_shared_string());
+            settings
+                .invoke_reset("student-data.immediate-sign-in-case-sensitive".to_shared_string());
+            settings.invoke_reset("student-data.id-trim".to_shared_string());
+            settings.invoke_reset("student-data.id-case".to_shared_string());
+            settings.invoke_reset("student-data.id-strip-leading-zeros".to_shared_string());
+            settings.invoke_reset("student-data.id-integer-floats".to_shared_string());
+            settings.invoke_reset("kiosk.name-search".to_shared_string());
+            settings.invoke_reset("kiosk.scanner-enabled".to_shared_string());
+            settings.invoke_reset("kiosk.scanner-prefix".to_shared_string());
+            settings.invoke_reset("kiosk.scanner-suffix".to_shared_string());
+            settings.invoke_reset("kiosk.scanner-hide-id-field".to_shared_string());
+            settings.invoke_reset("kiosk.greeting-duration".to_shared_string());
         }
     });
 }
@@ -179,24 +827,162 @@ fn load_config(ui: &App, config: &Config) {
 
     let settings = ui.global::<Settings>();
 
+    let today = OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+        .date()
+        .to_shared_string();
+    settings.set_export_from(today.clone());
+    settings.set_export_to(today);
+
     settings.set_my_studio(MyStudio {
         email: config.my_studio.email.clone().into(),
         company_id: config.my_studio.company_id.clone().into(),
     });
+    let warnings: Vec<String> = config.warnings().iter().map(ToString::to_string).collect();
+    settings.set_config_warnings(if warnings.is_empty() {
+        "No problems found".into()
+    } else {
+        warnings.join("\n").into()
+    });
+    settings.set_backup_available(config.has_backup());
+
+    settings.set_secret_store(secret_store_text(config));
 
+    let (name_column, name_header) = column_fields(&config.student_data.name_column);
+    let (id_column, id_header) = column_fields(&config.student_data.id_column);
+    let (immediate_sign_in_column, immediate_sign_in_header) =
+        column_fields(&config.student_data.immediate_sign_in.column);
+    settings.set_header_choices(ModelRc::new(VecModel::from(vec![
+        COLUMN_BY_INDEX.to_shared_string(),
+    ])));
     settings.set_student_data(StudentData {
+        name_column,
+        name_header,
+        id_column,
+        id_header,
+        immediate_sign_in_column,
+        immediate_sign_in_header,
         filepath: config.student_data.filepath.display().to_shared_string(),
         sheet_name: config.student_data.sheet_name.clone().into(),
-        name_column: config.student_data.name_column.into(),
-        id_column: config.student_data.id_column.into(),
-        immediate_sign_in_column: config.student_data.immediate_sign_in.column.into(),
+        delimiter: delimiter_text(config.student_data.delimiter),
+        encoding: config
+            .student_data
+            .encoding
+            .clone()
+            .unwrap_or_default()
+            .into(),
         immediate_sign_in_enabled_symbol: config
             .student_data
             .immediate_sign_in
             .enabled_symbol
             .clone()
             .into(),
+        immediate_sign_in_case_sensitive: config.student_data.immediate_sign_in.case_sensitive,
+        id_trim: config.student_data.id_normalization.trim,
+        id_case: id_case_text(config.student_data.id_normalization.case),
+        id_strip_leading_zeros: config.student_data.id_normalization.strip_leading_zeros,
+        id_integer_floats: config.student_data.id_normalization.integer_floats,
     });
+
+    settings.set_kiosk(Kiosk {
+        name_search: config.kiosk.name_search,
+        scanner_enabled: config.kiosk.scanner.enabled,
+        scanner_prefix: config.kiosk.scanner.prefix.clone().into(),
+        scanner_suffix: config.kiosk.scanner.suffix.clone().into(),
+        scanner_hide_id_field: config.kiosk.scanner.hide_id_field,
+        greeting_duration: greeting_duration_field(config),
+        lockdown: config.kiosk.lockdown,
+    });
+    settings.set_admin_pin_set(config.kiosk.has_admin_pin());
+}
+
+/// Describes where the credentials are stored, and whether they are lost without a secret store.
+fn secret_store_text(config: &Config) -> SharedString {
+    match config.secret_store() {
+        Some(store) => store.name().into(),
+        None if config.has_unsaved_credentials() => {
+            "None available, the email is not saved and is lost when youkoso closes".into()
+        }
+        None => "None available, credentials cannot be saved".into(),
+    }
+}
+
+/// Shows the result of the latest roster reload and what was loaded.
+fn show_roster_status(ui: &App, desk: &Desk, reload: &Reload) {
+    let roster = desk.roster().borrow();
+    let status = match reload {
+        Reload::Loaded { at, students, .. } => format!(
+            "{students} students, loaded at {:02}:{:02}:{:02}",
+            at.hour(),
+            at.minute(),
+            at.second()
+        ),
+        Reload::Failed { at, error } => format!(
+            "Reload failed at {:02}:{:02}:{:02}, keeping {} students: {error}",
+            at.hour(),
+            at.minute(),
+            at.second(),
+            roster.len()
+        ),
+    };
+    let settings = ui.global::<Settings>();
+    settings.set_roster_status(status.into());
+    // a failed reload keeps the previous roster, so its report still applies
+    if let Reload::Loaded { report, .. } = reload {
+        settings.set_roster_report(report.to_string().into());
+    }
+
+    show_immediate_sign_in_rows(ui, &immediate_sign_in_rows(&roster));
+}
+
+/// Returns the configuration key of the setting with the given reset identifier, e.g.
+/// `"student_data.immediate_sign_in.column"` for `"student-data.immediate-sign-in-column"`.
+fn setting_key(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "theme" => "theme",
+        "my-studio.email" => "my_studio.email",
+        "my-studio.company-id" => "my_studio.company_id",
+        "student-data.filepath" => "student_data.filepath",
+        "student-data.sheet-name" => "student_data.sheet_name",
+        "student-data.delimiter" => "student_data.delimiter",
+        "student-data.encoding" => "student_data.encoding",
+        "student-data.name-column" => "student_data.name_column",
+        "student-data.id-column" => "student_data.id_column",
+        "student-data.immediate-sign-in-column" => "student_data.immediate_sign_in.column",
+        "student-data.immediate-sign-in-enabled-symbol" => {
+            "student_data.immediate_sign_in.enabled_symbol"
+        }
+        "student-data.immediate-sign-in-case-sensitive" => {
+            "student_data.immediate_sign_in.case_sensitive"
+        }
+        "student-data.id-trim" => "student_data.id_normalization.trim",
+        "student-data.id-case" => "student_data.id_normalization.case",
+        "student-data.id-strip-leading-zeros" => {
+            "student_data.id_normalization.strip_leading_zeros"
+        }
+        "student-data.id-integer-floats" => "student_data.id_normalization.integer_floats",
+        "kiosk.name-search" => "kiosk.name_search",
+        "kiosk.scanner-enabled" => "kiosk.scanner.enabled",
+        "kiosk.scanner-prefix" => "kiosk.scanner.prefix",
+        "kiosk.scanner-suffix" => "kiosk.scanner.suffix",
+        "kiosk.scanner-hide-id-field" => "kiosk.scanner.hide_id_field",
+        "kiosk.greeting-duration" => "kiosk.greeting.duration_secs",
+        "kiosk.lockdown" => "kiosk.lockdown",
+        "kiosk.admin-pin" => "kiosk.admin_pin_hash",
+        _ => return None,
+    })
+}
+
+/// Shows which spreadsheet rows currently allow immediate sign-in.
+fn show_immediate_sign_in_rows(ui: &App, rows: &[u32]) {
+    let text = if rows.is_empty() {
+        "No rows match".to_owned()
+    } else {
+        let rows: Vec<String> = rows.iter().map(u32::to_string).collect();
+        format!("Rows {}", rows.join(", "))
+    };
+    ui.global::<Settings>()
+        .set_immediate_sign_in_rows(text.into());
 }
 
 fn save_to_config(ui: &App, config: &mut Config) {
@@ -214,15 +1000,124 @@ fn save_to_config(ui: &App, config: &mut Config) {
 
     config.student_data.filepath = settings.get_student_data().filepath.to_string().into();
     config.student_data.sheet_name = settings.get_student_data().sheet_name.into();
-    config.student_data.name_column = settings.get_student_data().name_column.try_into().unwrap();
-    config.student_data.id_column = settings.get_student_data().id_column.try_into().unwrap();
-    config.student_data.immediate_sign_in.column = settings
-        .get_student_data()
-        .immediate_sign_in_column
-        .try_into()
-        .unwrap();
+    config.student_data.delimiter = parse_delimiter(&settings.get_student_data().delimiter);
+    config.student_data.encoding = Some(settings.get_student_data().encoding.trim().to_owned())
+        .filter(|encoding| !encoding.is_empty());
+    config.student_data.name_column = column_from_fields(
+        settings.get_student_data().name_column,
+        settings.get_student_data().name_header,
+        &config.student_data.name_column,
+    );
+    config.student_data.id_column = column_from_fields(
+        settings.get_student_data().id_column,
+        settings.get_student_data().id_header,
+        &config.student_data.id_column,
+    );
+    config.student_data.immediate_sign_in.column = column_from_fields(
+        settings.get_student_data().immediate_sign_in_column,
+        settings.get_student_data().immediate_sign_in_header,
+        &config.student_data.immediate_sign_in.column,
+    );
     config.student_data.immediate_sign_in.enabled_symbol = settings
         .get_student_data()
         .immediate_sign_in_enabled_symbol
         .into();
+    config.student_data.immediate_sign_in.case_sensitive =
+        settings.get_student_data().immediate_sign_in_case_sensitive;
+    config.student_data.id_normalization.trim = settings.get_student_data().id_trim;
+    config.student_data.id_normalization.case = parse_id_case(&settings.get_student_data().id_case);
+    config.student_data.id_normalization.strip_leading_zeros =
+        settings.get_student_data().id_strip_leading_zeros;
+    config.student_data.id_normalization.integer_floats =
+        settings.get_student_data().id_integer_floats;
+
+    config.kiosk.name_search = settings.get_kiosk().name_search;
+    config.kiosk.scanner.enabled = settings.get_kiosk().scanner_enabled;
+    config.kiosk.scanner.prefix = settings.get_kiosk().scanner_prefix.into();
+    config.kiosk.scanner.suffix = settings.get_kiosk().scanner_suffix.into();
+    config.kiosk.scanner.hide_id_field = settings.get_kiosk().scanner_hide_id_field;
+    config.kiosk.greeting.duration_secs = settings
+        .get_kiosk()
+        .greeting_duration
+        .try_into()
+        .unwrap_or_default();
+    config.kiosk.lockdown = settings.get_kiosk().lockdown;
+}
+
+/// Splits a column into the index and header edited by a `ColumnPicker`. An empty header means
+/// the column is mapped by index, otherwise the index is the fallback for a missing header.
+fn column_fields(column: &Column) -> (i32, SharedString) {
+    match column {
+        Column::Index(index) => ((*index).into(), SharedString::new()),
+        Column::Header(header) => (0, header.into()),
+        Column::HeaderOrIndex { header, index } => ((*index).into(), header.into()),
+    }
+}
+
+/// Builds a column from the index and header edited by a `ColumnPicker`.
+///
+/// A column mapped by a header alone is shown with index 0, so it is kept as it is unless the
+/// header or the index was changed.
+fn column_from_fields(index: i32, header: SharedString, current: &Column) -> Column {
+    let index = index.try_into().unwrap();
+    if header.is_empty() {
+        return Column::Index(index);
+    }
+
+    match current {
+        Column::Header(current) if header == *current && index == 0 => {
+            Column::Header(header.into())
+        }
+        _ => Column::HeaderOrIndex {
+            header: header.into(),
+            index,
+        },
+    }
+}
+
+/// Formats a CSV delimiter for editing, writing a tab as `\t` so that it is visible.
+fn delimiter_text(delimiter: Option<char>) -> SharedString {
+    match delimiter {
+        Some('\t') => "\\t".into(),
+        Some(delimiter) => delimiter.to_shared_string(),
+        None => SharedString::new(),
+    }
+}
+
+/// Parses a CSV delimiter entered in the settings page. An empty field means the delimiter is
+/// chosen by file extension.
+fn parse_delimiter(text: &str) -> Option<char> {
+    match text {
+        "" => None,
+        "\\t" | "tab" => Some('\t'),
+        text => text.chars().next(),
+    }
+}
+
+/// Returns the greeting duration as edited by an `IntLineEdit`.
+fn greeting_duration_field(config: &Config) -> i32 {
+    config
+        .kiosk
+        .greeting
+        .duration_secs
+        .try_into()
+        .unwrap_or(i32::MAX)
+}
+
+/// Formats an ID case as one of the choices of the settings page.
+fn id_case_text(case: IdCase) -> SharedString {
+    match case {
+        IdCase::Preserve => "Preserve".into(),
+        IdCase::Lower => "Lowercase".into(),
+        IdCase::Upper => "Uppercase".into(),
+    }
+}
+
+/// Parses an ID case chosen on the settings page.
+fn parse_id_case(text: &str) -> IdCase {
+    match text {
+        "Lowercase" => IdCase::Lower,
+        "Uppercase" => IdCase::Upper,
+        _ => IdCase::Preserve,
+    }
 }